@@ -1,24 +1,229 @@
 #![allow(non_upper_case_globals)]
-use soroban_sdk::storage::{Instance, Persistent};
-use soroban_sdk::{panic_with_error, Address, Env};
-
-use crate::types;
-
-use types::{error::Error, subscription::Subscription};
+use soroban_sdk::storage::{Instance, Persistent, Temporary};
+use soroban_sdk::{panic_with_error, Address, Bytes, BytesN, Env, Map, String, Symbol, TryFromVal, Val, Vec};
+
+use crate::{types, ACTIVATION_FEE_FACTOR, DAY, MAX_WEBHOOK_SIZE, MIN_HEARTBEAT};
+
+use types::{
+    asset::Asset,
+    error::Error,
+    subscription::{Subscription, SUBSCRIPTION_FORMAT_VERSION},
+    suspend_behavior::SuspendBehavior,
+    ticker_asset::TickerAsset,
+};
 const ADMIN_KEY: &str = "admin";
+const PENDING_ADMIN_KEY: &str = "pend_adm";
 const BASE_FEE: &str = "base_fee";
 const LAST_SUBSCRIPTION_ID: &str = "last";
 const TOKEN_KEY: &str = "token";
+const WEBHOOK_BYTE_FEE: &str = "byte_fee";
+const MIN_DEPOSIT_INTERVAL: &str = "dep_intvl";
+const FEE_HISTORY: &str = "fee_hist";
+const REGISTRY_KEY: &str = "registry";
+const MAX_PRIORITY: &str = "max_prio";
+const TVL_KEY: &str = "tvl";
+const MAX_TVL_KEY: &str = "max_tvl";
+const RECREATE_COOLDOWN: &str = "recr_cd";
+const PRORATE_CHARGE: &str = "prorate";
+const BILLING_PAUSED: &str = "bill_paus";
+const PAUSED: &str = "paused";
+const MIN_HEARTBEAT_KEY: &str = "min_hbeat";
+const ACTIVATION_REFUND: &str = "act_rfnd";
+const FEE_FACTOR: &str = "fee_fact";
+const EVENT_SEQ: &str = "event_seq";
+const INSTANCE_TTL: &str = "inst_ttl";
+const MAX_PER_OWNER: &str = "max_owner";
+const MAX_WEBHOOK_SIZE_KEY: &str = "max_whook";
+
+// Default instance TTL threshold and extension in ledgers, a week and a month
+const INSTANCE_TTL_THRESHOLD: u32 = 17280 * 7;
+const INSTANCE_TTL_EXTEND_TO: u32 = 17280 * 30;
+const CHARGE_INTERVAL_BOUNDS: &str = "intvl_bnd";
+const DEBUG_EVENTS: &str = "debug_evt";
+const MIN_RESERVE: &str = "min_resrv";
+const PRE_SUSPEND_LEAD_DAYS: &str = "pre_susp";
+const SUSPEND_GRACE: &str = "susp_grac";
+const SUSPEND_BEHAVIOR: &str = "susp_bhv";
+const CHARGER_KEY: &str = "charger";
+const ACTIVE_COUNT: &str = "act_count";
+const CAPPED_FEES: &str = "cap_fees";
+const UNCAPPED_COUNT: &str = "unc_count";
+const UNCAPPED_BYTES: &str = "unc_bytes";
+const ACTIVE_BALANCE: &str = "act_bal";
+const SUSPENDED_BALANCE: &str = "susp_bal";
+const LAST_TRIGGER: &str = "last_trig";
+const TRIGGER_HISTORY: &str = "trig_hist";
+const MINIMAL_EVENTS: &str = "min_evts";
+const ASSET_INTERVAL: &str = "asset_int";
+const SOURCE_CAP: &str = "src_cap";
+const SOURCE_COUNT: &str = "src_count";
+const TOKEN_ALLOWED: &str = "tkn_allow";
 
 pub trait EnvExtensions {
     fn get_admin(&self) -> Option<Address>;
 
     fn set_admin(&self, admin: &Address);
 
+    fn get_pending_admin(&self) -> Option<Address>;
+
+    fn set_pending_admin(&self, pending_admin: &Option<Address>);
+
     fn get_fee(&self) -> u64;
 
     fn set_fee(&self, base_fee: u64);
 
+    fn get_fee_history(&self) -> Vec<(u64, u64)>;
+
+    fn set_fee_history(&self, fee_history: &Vec<(u64, u64)>);
+
+    fn get_webhook_byte_fee(&self) -> u64;
+
+    fn set_webhook_byte_fee(&self, webhook_byte_fee: u64);
+
+    fn get_max_priority(&self) -> u32;
+
+    fn set_max_priority(&self, max_priority: u32);
+
+    fn get_min_heartbeat(&self) -> u32;
+
+    fn set_min_heartbeat(&self, min_heartbeat: u32);
+
+    fn get_max_webhook_size(&self) -> u32;
+
+    fn set_max_webhook_size(&self, size: u32);
+
+    fn get_event_seq(&self) -> u64;
+
+    fn set_event_seq(&self, event_seq: u64);
+
+    fn get_fee_factor(&self) -> u32;
+
+    fn set_fee_factor(&self, factor: u32);
+
+    fn get_activation_refund_percent(&self) -> u32;
+
+    fn set_activation_refund_percent(&self, percent: u32);
+
+    fn get_charge_interval_bounds(&self) -> (u64, u64);
+
+    fn set_charge_interval_bounds(&self, bounds: &(u64, u64));
+
+    fn get_debug_events(&self) -> bool;
+
+    fn set_debug_events(&self, debug_events: bool);
+
+    fn get_minimal_events(&self) -> bool;
+
+    fn set_minimal_events(&self, minimal_events: bool);
+
+    fn is_billing_paused(&self) -> bool;
+
+    fn set_billing_paused(&self, billing_paused: bool);
+
+    fn is_paused(&self) -> bool;
+
+    fn set_paused(&self, paused: bool);
+
+    fn get_prorate_charge(&self) -> bool;
+
+    fn set_prorate_charge(&self, prorate_charge: bool);
+
+    fn get_recreate_cooldown(&self) -> u64;
+
+    fn set_recreate_cooldown(&self, recreate_cooldown: u64);
+
+    fn get_last_cancel(&self, owner: &Address, base: &TickerAsset, quote: &TickerAsset) -> Option<u64>;
+
+    fn set_last_cancel(&self, owner: &Address, base: &TickerAsset, quote: &TickerAsset, timestamp: u64, ledgers_to_live: u32);
+
+    fn get_asset_symbol(&self, asset: &Asset) -> Option<Symbol>;
+
+    fn set_asset_symbol(&self, asset: &Asset, symbol: &Symbol);
+
+    fn get_asset_interval(&self, asset: &Asset) -> Option<u64>;
+
+    fn get_source_cap(&self, source: &String) -> Option<u32>;
+
+    fn set_source_cap(&self, source: &String, cap: u32);
+
+    fn get_source_count(&self, source: &String) -> u32;
+
+    fn set_source_count(&self, source: &String, count: u32);
+
+    fn set_asset_interval(&self, asset: &Asset, interval_ms: u64);
+
+    fn is_token_allowed(&self, token: &Address) -> bool;
+
+    fn set_token_allowed(&self, token: &Address, allowed: bool);
+
+    fn get_min_deposit_interval(&self) -> u64;
+
+    fn set_min_deposit_interval(&self, min_deposit_interval: u64);
+
+    fn get_registry(&self) -> Option<Address>;
+
+    fn set_registry(&self, registry: &Option<Address>);
+
+    fn get_charger(&self) -> Option<Address>;
+
+    fn get_last_trigger(&self) -> Option<(u64, BytesN<32>)>;
+
+    fn set_last_trigger(&self, timestamp: u64, trigger_hash: &BytesN<32>);
+
+    fn get_trigger_history(&self) -> Vec<(u64, BytesN<32>)>;
+
+    fn set_trigger_history(&self, trigger_history: &Vec<(u64, BytesN<32>)>);
+
+    fn set_charger(&self, charger: &Option<Address>);
+
+    fn get_tvl(&self, token: &Address) -> u64;
+
+    fn set_tvl(&self, token: &Address, tvl: u64);
+
+    fn get_active_count(&self) -> u64;
+
+    fn set_active_count(&self, active_count: u64);
+
+    fn get_capped_fees(&self) -> u64;
+
+    fn set_capped_fees(&self, capped_fees: u64);
+
+    fn get_uncapped_count(&self) -> u64;
+
+    fn set_uncapped_count(&self, uncapped_count: u64);
+
+    fn get_uncapped_bytes(&self) -> u64;
+
+    fn set_uncapped_bytes(&self, uncapped_bytes: u64);
+
+    fn get_active_balance(&self) -> u64;
+
+    fn set_active_balance(&self, active_balance: u64);
+
+    fn get_suspended_balance(&self) -> u64;
+
+    fn set_suspended_balance(&self, suspended_balance: u64);
+
+    fn get_max_tvl(&self) -> Option<u64>;
+
+    fn set_max_tvl(&self, max_tvl: &Option<u64>);
+
+    fn get_suspend_behavior(&self) -> SuspendBehavior;
+
+    fn set_suspend_behavior(&self, suspend_behavior: &SuspendBehavior);
+
+    fn get_min_reserve(&self) -> u64;
+
+    fn set_min_reserve(&self, min_reserve: u64);
+
+    fn get_pre_suspend_lead_days(&self) -> u32;
+
+    fn set_pre_suspend_lead_days(&self, pre_suspend_lead_days: u32);
+
+    fn get_suspend_grace(&self) -> u64;
+
+    fn set_suspend_grace(&self, suspend_grace: u64);
+
     fn get_token(&self) -> Address;
 
     fn set_token(&self, token: &Address);
@@ -35,6 +240,22 @@ pub trait EnvExtensions {
 
     fn extend_subscription_ttl(&self, subscription_id: u64, extend_to: u32);
 
+    fn get_owner_subscriptions(&self, owner: &Address) -> Vec<u64>;
+
+    fn set_owner_subscriptions(&self, owner: &Address, subscription_ids: &Vec<u64>);
+
+    fn extend_owner_subscriptions_ttl(&self, owner: &Address, extend_to: u32);
+
+    fn get_max_per_owner(&self) -> u32;
+
+    fn set_max_per_owner(&self, limit: u32);
+
+    fn get_instance_ttl(&self) -> (u32, u32);
+
+    fn set_instance_ttl(&self, instance_ttl: &(u32, u32));
+
+    fn extend_instance_ttl(&self);
+
     fn panic_if_not_admin(&self);
 
     fn is_initialized(&self) -> bool;
@@ -42,57 +263,473 @@ pub trait EnvExtensions {
 
 impl EnvExtensions for Env {
     fn is_initialized(&self) -> bool {
-        get_instance_storage(&self).has(&ADMIN_KEY)
+        get_instance_storage(self).has(&ADMIN_KEY)
     }
 
     fn get_admin(&self) -> Option<Address> {
-        get_instance_storage(&self).get(&ADMIN_KEY)
+        get_instance_storage(self).get(&ADMIN_KEY)
     }
 
     fn set_admin(&self, admin: &Address) {
-        get_instance_storage(&self).set(&ADMIN_KEY, admin);
+        get_instance_storage(self).set(&ADMIN_KEY, admin);
+    }
+
+    fn get_pending_admin(&self) -> Option<Address> {
+        get_instance_storage(self).get(&PENDING_ADMIN_KEY)
+    }
+
+    fn set_pending_admin(&self, pending_admin: &Option<Address>) {
+        match pending_admin {
+            Some(pending_admin) => get_instance_storage(self).set(&PENDING_ADMIN_KEY, pending_admin),
+            None => get_instance_storage(self).remove(&PENDING_ADMIN_KEY),
+        }
     }
 
     fn get_fee(&self) -> u64 {
-        get_instance_storage(&self).get(&BASE_FEE).unwrap_or(0)
+        get_instance_storage(self).get(&BASE_FEE).unwrap_or(0)
     }
 
     fn set_fee(&self, base_fee: u64) {
-        get_instance_storage(&self).set(&BASE_FEE, &base_fee);
+        get_instance_storage(self).set(&BASE_FEE, &base_fee);
+    }
+
+    fn get_fee_history(&self) -> Vec<(u64, u64)> {
+        get_instance_storage(self)
+            .get(&FEE_HISTORY)
+            .unwrap_or_else(|| Vec::new(self))
+    }
+
+    fn set_fee_history(&self, fee_history: &Vec<(u64, u64)>) {
+        get_instance_storage(self).set(&FEE_HISTORY, fee_history);
+    }
+
+    fn get_webhook_byte_fee(&self) -> u64 {
+        get_instance_storage(self).get(&WEBHOOK_BYTE_FEE).unwrap_or(0)
+    }
+
+    fn set_webhook_byte_fee(&self, webhook_byte_fee: u64) {
+        get_instance_storage(self).set(&WEBHOOK_BYTE_FEE, &webhook_byte_fee);
+    }
+
+    fn get_max_priority(&self) -> u32 {
+        get_instance_storage(self).get(&MAX_PRIORITY).unwrap_or(0)
+    }
+
+    fn set_max_priority(&self, max_priority: u32) {
+        get_instance_storage(self).set(&MAX_PRIORITY, &max_priority);
+    }
+
+    fn get_min_heartbeat(&self) -> u32 {
+        get_instance_storage(self).get(&MIN_HEARTBEAT_KEY).unwrap_or(MIN_HEARTBEAT)
+    }
+
+    fn set_min_heartbeat(&self, min_heartbeat: u32) {
+        get_instance_storage(self).set(&MIN_HEARTBEAT_KEY, &min_heartbeat);
+    }
+
+    fn get_max_webhook_size(&self) -> u32 {
+        get_instance_storage(self).get(&MAX_WEBHOOK_SIZE_KEY).unwrap_or(MAX_WEBHOOK_SIZE)
+    }
+
+    fn set_max_webhook_size(&self, size: u32) {
+        get_instance_storage(self).set(&MAX_WEBHOOK_SIZE_KEY, &size);
+    }
+
+    fn get_event_seq(&self) -> u64 {
+        get_instance_storage(self).get(&EVENT_SEQ).unwrap_or(0)
+    }
+
+    fn set_event_seq(&self, event_seq: u64) {
+        get_instance_storage(self).set(&EVENT_SEQ, &event_seq);
+    }
+
+    fn get_fee_factor(&self) -> u32 {
+        get_instance_storage(self).get(&FEE_FACTOR).unwrap_or(ACTIVATION_FEE_FACTOR)
+    }
+
+    fn set_fee_factor(&self, factor: u32) {
+        get_instance_storage(self).set(&FEE_FACTOR, &factor);
+    }
+
+    fn get_activation_refund_percent(&self) -> u32 {
+        get_instance_storage(self).get(&ACTIVATION_REFUND).unwrap_or(0)
+    }
+
+    fn set_activation_refund_percent(&self, percent: u32) {
+        get_instance_storage(self).set(&ACTIVATION_REFUND, &percent);
+    }
+
+    fn get_charge_interval_bounds(&self) -> (u64, u64) {
+        get_instance_storage(self)
+            .get(&CHARGE_INTERVAL_BOUNDS)
+            .unwrap_or((DAY, DAY))
+    }
+
+    fn set_charge_interval_bounds(&self, bounds: &(u64, u64)) {
+        get_instance_storage(self).set(&CHARGE_INTERVAL_BOUNDS, bounds);
+    }
+
+    fn get_debug_events(&self) -> bool {
+        get_instance_storage(self).get(&DEBUG_EVENTS).unwrap_or(false)
+    }
+
+    fn set_debug_events(&self, debug_events: bool) {
+        get_instance_storage(self).set(&DEBUG_EVENTS, &debug_events);
+    }
+
+    fn get_minimal_events(&self) -> bool {
+        get_instance_storage(self).get(&MINIMAL_EVENTS).unwrap_or(false)
+    }
+
+    fn set_minimal_events(&self, minimal_events: bool) {
+        get_instance_storage(self).set(&MINIMAL_EVENTS, &minimal_events);
+    }
+
+    fn is_billing_paused(&self) -> bool {
+        get_instance_storage(self).get(&BILLING_PAUSED).unwrap_or(false)
+    }
+
+    fn set_billing_paused(&self, billing_paused: bool) {
+        get_instance_storage(self).set(&BILLING_PAUSED, &billing_paused);
+    }
+
+    fn is_paused(&self) -> bool {
+        get_instance_storage(self).get(&PAUSED).unwrap_or(false)
+    }
+
+    fn set_paused(&self, paused: bool) {
+        get_instance_storage(self).set(&PAUSED, &paused);
+    }
+
+    fn get_prorate_charge(&self) -> bool {
+        get_instance_storage(self).get(&PRORATE_CHARGE).unwrap_or(false)
+    }
+
+    fn set_prorate_charge(&self, prorate_charge: bool) {
+        get_instance_storage(self).set(&PRORATE_CHARGE, &prorate_charge);
+    }
+
+    fn get_recreate_cooldown(&self) -> u64 {
+        get_instance_storage(self).get(&RECREATE_COOLDOWN).unwrap_or(0)
+    }
+
+    fn set_recreate_cooldown(&self, recreate_cooldown: u64) {
+        get_instance_storage(self).set(&RECREATE_COOLDOWN, &recreate_cooldown);
+    }
+
+    fn get_last_cancel(&self, owner: &Address, base: &TickerAsset, quote: &TickerAsset) -> Option<u64> {
+        get_temporary_storage(self).get(&(owner.clone(), base.clone(), quote.clone()))
+    }
+
+    fn set_last_cancel(&self, owner: &Address, base: &TickerAsset, quote: &TickerAsset, timestamp: u64, ledgers_to_live: u32) {
+        let key = (owner.clone(), base.clone(), quote.clone());
+        let storage = get_temporary_storage(self);
+        storage.set(&key, &timestamp);
+        storage.extend_ttl(&key, ledgers_to_live, ledgers_to_live);
+    }
+
+    fn get_asset_symbol(&self, asset: &Asset) -> Option<Symbol> {
+        get_persistent_storage(self).get(asset)
+    }
+
+    fn set_asset_symbol(&self, asset: &Asset, symbol: &Symbol) {
+        let storage = get_persistent_storage(self);
+        storage.set(asset, symbol);
+        // display names are not funded by subscriptions, keep them alive as long as possible
+        let max_ttl = self.storage().max_ttl();
+        storage.extend_ttl(asset, max_ttl, max_ttl);
+    }
+
+    fn get_asset_interval(&self, asset: &Asset) -> Option<u64> {
+        get_persistent_storage(self).get(&(ASSET_INTERVAL, asset.clone()))
+    }
+
+    fn set_asset_interval(&self, asset: &Asset, interval_ms: u64) {
+        let key = (ASSET_INTERVAL, asset.clone());
+        let storage = get_persistent_storage(self);
+        storage.set(&key, &interval_ms);
+        let max_ttl = self.storage().max_ttl();
+        storage.extend_ttl(&key, max_ttl, max_ttl);
+    }
+
+    fn is_token_allowed(&self, token: &Address) -> bool {
+        get_persistent_storage(self).has(&(TOKEN_ALLOWED, token.clone()))
+    }
+
+    fn set_token_allowed(&self, token: &Address, allowed: bool) {
+        let key = (TOKEN_ALLOWED, token.clone());
+        let storage = get_persistent_storage(self);
+        if !allowed {
+            storage.remove(&key);
+            return;
+        }
+        storage.set(&key, &true);
+        let max_ttl = self.storage().max_ttl();
+        storage.extend_ttl(&key, max_ttl, max_ttl);
+    }
+
+    fn get_source_cap(&self, source: &String) -> Option<u32> {
+        get_persistent_storage(self).get(&(SOURCE_CAP, source.clone()))
+    }
+
+    fn set_source_cap(&self, source: &String, cap: u32) {
+        let key = (SOURCE_CAP, source.clone());
+        let storage = get_persistent_storage(self);
+        storage.set(&key, &cap);
+        let max_ttl = self.storage().max_ttl();
+        storage.extend_ttl(&key, max_ttl, max_ttl);
+    }
+
+    fn get_source_count(&self, source: &String) -> u32 {
+        get_persistent_storage(self)
+            .get(&(SOURCE_COUNT, source.clone()))
+            .unwrap_or(0)
+    }
+
+    fn set_source_count(&self, source: &String, count: u32) {
+        let key = (SOURCE_COUNT, source.clone());
+        let storage = get_persistent_storage(self);
+        if count == 0 {
+            storage.remove(&key);
+            return;
+        }
+        storage.set(&key, &count);
+        let max_ttl = self.storage().max_ttl();
+        storage.extend_ttl(&key, max_ttl, max_ttl);
+    }
+
+    fn get_min_deposit_interval(&self) -> u64 {
+        get_instance_storage(self).get(&MIN_DEPOSIT_INTERVAL).unwrap_or(0)
+    }
+
+    fn set_min_deposit_interval(&self, min_deposit_interval: u64) {
+        get_instance_storage(self).set(&MIN_DEPOSIT_INTERVAL, &min_deposit_interval);
+    }
+
+    fn get_registry(&self) -> Option<Address> {
+        get_instance_storage(self).get(&REGISTRY_KEY)
+    }
+
+    fn set_registry(&self, registry: &Option<Address>) {
+        match registry {
+            Some(registry) => get_instance_storage(self).set(&REGISTRY_KEY, registry),
+            None => get_instance_storage(self).remove(&REGISTRY_KEY),
+        }
+    }
+
+    fn get_charger(&self) -> Option<Address> {
+        get_instance_storage(self).get(&CHARGER_KEY)
+    }
+
+    fn get_last_trigger(&self) -> Option<(u64, BytesN<32>)> {
+        get_instance_storage(self).get(&LAST_TRIGGER)
+    }
+
+    fn set_last_trigger(&self, timestamp: u64, trigger_hash: &BytesN<32>) {
+        get_instance_storage(self).set(&LAST_TRIGGER, &(timestamp, trigger_hash.clone()));
+    }
+
+    fn get_trigger_history(&self) -> Vec<(u64, BytesN<32>)> {
+        get_instance_storage(self)
+            .get(&TRIGGER_HISTORY)
+            .unwrap_or_else(|| Vec::new(self))
+    }
+
+    fn set_trigger_history(&self, trigger_history: &Vec<(u64, BytesN<32>)>) {
+        get_instance_storage(self).set(&TRIGGER_HISTORY, trigger_history);
+    }
+
+    fn set_charger(&self, charger: &Option<Address>) {
+        match charger {
+            Some(charger) => get_instance_storage(self).set(&CHARGER_KEY, charger),
+            None => get_instance_storage(self).remove(&CHARGER_KEY),
+        }
+    }
+
+    fn get_tvl(&self, token: &Address) -> u64 {
+        // the contract token total predates the per-token totals
+        if *token == self.get_token() {
+            return get_instance_storage(self).get(&TVL_KEY).unwrap_or(0);
+        }
+        get_instance_storage(self).get(&(TVL_KEY, token.clone())).unwrap_or(0)
+    }
+
+    fn set_tvl(&self, token: &Address, tvl: u64) {
+        if *token == self.get_token() {
+            get_instance_storage(self).set(&TVL_KEY, &tvl);
+        } else {
+            get_instance_storage(self).set(&(TVL_KEY, token.clone()), &tvl);
+        }
+    }
+
+    fn get_active_count(&self) -> u64 {
+        get_instance_storage(self).get(&ACTIVE_COUNT).unwrap_or(0)
+    }
+
+    fn set_active_count(&self, active_count: u64) {
+        get_instance_storage(self).set(&ACTIVE_COUNT, &active_count);
+    }
+
+    fn get_capped_fees(&self) -> u64 {
+        get_instance_storage(self).get(&CAPPED_FEES).unwrap_or(0)
+    }
+
+    fn set_capped_fees(&self, capped_fees: u64) {
+        get_instance_storage(self).set(&CAPPED_FEES, &capped_fees);
+    }
+
+    fn get_uncapped_count(&self) -> u64 {
+        get_instance_storage(self).get(&UNCAPPED_COUNT).unwrap_or(0)
+    }
+
+    fn set_uncapped_count(&self, uncapped_count: u64) {
+        get_instance_storage(self).set(&UNCAPPED_COUNT, &uncapped_count);
+    }
+
+    fn get_uncapped_bytes(&self) -> u64 {
+        get_instance_storage(self).get(&UNCAPPED_BYTES).unwrap_or(0)
+    }
+
+    fn set_uncapped_bytes(&self, uncapped_bytes: u64) {
+        get_instance_storage(self).set(&UNCAPPED_BYTES, &uncapped_bytes);
+    }
+
+    fn get_active_balance(&self) -> u64 {
+        get_instance_storage(self).get(&ACTIVE_BALANCE).unwrap_or(0)
+    }
+
+    fn set_active_balance(&self, active_balance: u64) {
+        get_instance_storage(self).set(&ACTIVE_BALANCE, &active_balance);
+    }
+
+    fn get_suspended_balance(&self) -> u64 {
+        get_instance_storage(self).get(&SUSPENDED_BALANCE).unwrap_or(0)
+    }
+
+    fn set_suspended_balance(&self, suspended_balance: u64) {
+        get_instance_storage(self).set(&SUSPENDED_BALANCE, &suspended_balance);
+    }
+
+    fn get_max_tvl(&self) -> Option<u64> {
+        get_instance_storage(self).get(&MAX_TVL_KEY)
+    }
+
+    fn set_max_tvl(&self, max_tvl: &Option<u64>) {
+        match max_tvl {
+            Some(max_tvl) => get_instance_storage(self).set(&MAX_TVL_KEY, max_tvl),
+            None => get_instance_storage(self).remove(&MAX_TVL_KEY),
+        }
+    }
+
+    fn get_suspend_behavior(&self) -> SuspendBehavior {
+        get_instance_storage(self)
+            .get(&SUSPEND_BEHAVIOR)
+            .unwrap_or(SuspendBehavior::Retain)
+    }
+
+    fn set_suspend_behavior(&self, suspend_behavior: &SuspendBehavior) {
+        get_instance_storage(self).set(&SUSPEND_BEHAVIOR, suspend_behavior);
+    }
+
+    fn get_min_reserve(&self) -> u64 {
+        get_instance_storage(self).get(&MIN_RESERVE).unwrap_or(0)
+    }
+
+    fn set_min_reserve(&self, min_reserve: u64) {
+        get_instance_storage(self).set(&MIN_RESERVE, &min_reserve);
+    }
+
+    fn get_pre_suspend_lead_days(&self) -> u32 {
+        get_instance_storage(self).get(&PRE_SUSPEND_LEAD_DAYS).unwrap_or(0)
+    }
+
+    fn set_pre_suspend_lead_days(&self, pre_suspend_lead_days: u32) {
+        get_instance_storage(self).set(&PRE_SUSPEND_LEAD_DAYS, &pre_suspend_lead_days);
+    }
+
+    fn get_suspend_grace(&self) -> u64 {
+        get_instance_storage(self).get(&SUSPEND_GRACE).unwrap_or(0)
+    }
+
+    fn set_suspend_grace(&self, suspend_grace: u64) {
+        get_instance_storage(self).set(&SUSPEND_GRACE, &suspend_grace);
     }
 
     fn get_token(&self) -> Address {
-        get_instance_storage(&self).get(&TOKEN_KEY).unwrap()
+        get_instance_storage(self).get(&TOKEN_KEY).unwrap()
     }
 
     fn set_token(&self, token: &Address) {
-        get_instance_storage(&self).set(&TOKEN_KEY, token);
+        get_instance_storage(self).set(&TOKEN_KEY, token);
     }
 
     fn get_last_subscription_id(&self) -> u64 {
-        get_instance_storage(&self)
+        get_instance_storage(self)
             .get(&LAST_SUBSCRIPTION_ID)
             .unwrap_or(0)
     }
 
     fn set_last_subscription_id(&self, last_subscription_id: u64) {
-        get_instance_storage(&self).set(&LAST_SUBSCRIPTION_ID, &last_subscription_id);
+        get_instance_storage(self).set(&LAST_SUBSCRIPTION_ID, &last_subscription_id);
     }
 
     fn get_subscription(&self, subscription_id: u64) -> Option<Subscription> {
-        get_persistent_storage(&self).get(&subscription_id)
+        let fields: Map<Symbol, Val> = get_persistent_storage(self).get(&subscription_id)?;
+        Some(upgrade_subscription(self, &fields))
     }
 
     fn set_subscription(&self, subscription_id: u64, subscription: &Subscription) {
-        get_persistent_storage(&self).set(&subscription_id, subscription);
+        get_persistent_storage(self).set(&subscription_id, subscription);
     }
 
     fn remove_subscription(&self, subscription_id: u64) {
-        get_persistent_storage(&self).remove(&subscription_id);
+        get_persistent_storage(self).remove(&subscription_id);
     }
 
     fn extend_subscription_ttl(&self, subscription_id: u64, extend_to: u32) {
-        get_persistent_storage(&self).extend_ttl(&subscription_id, extend_to, extend_to)
+        get_persistent_storage(self).extend_ttl(&subscription_id, extend_to, extend_to)
+    }
+
+    fn get_owner_subscriptions(&self, owner: &Address) -> Vec<u64> {
+        get_persistent_storage(self)
+            .get(owner)
+            .unwrap_or_else(|| Vec::new(self))
+    }
+
+    fn set_owner_subscriptions(&self, owner: &Address, subscription_ids: &Vec<u64>) {
+        if subscription_ids.is_empty() {
+            get_persistent_storage(self).remove(owner);
+        } else {
+            get_persistent_storage(self).set(owner, subscription_ids);
+        }
+    }
+
+    fn extend_owner_subscriptions_ttl(&self, owner: &Address, extend_to: u32) {
+        get_persistent_storage(self).extend_ttl(owner, extend_to, extend_to)
+    }
+
+    fn get_max_per_owner(&self) -> u32 {
+        get_instance_storage(self).get(&MAX_PER_OWNER).unwrap_or(0)
+    }
+
+    fn set_max_per_owner(&self, limit: u32) {
+        get_instance_storage(self).set(&MAX_PER_OWNER, &limit);
+    }
+
+    fn get_instance_ttl(&self) -> (u32, u32) {
+        get_instance_storage(self)
+            .get(&INSTANCE_TTL)
+            .unwrap_or((INSTANCE_TTL_THRESHOLD, INSTANCE_TTL_EXTEND_TO))
+    }
+
+    fn set_instance_ttl(&self, instance_ttl: &(u32, u32)) {
+        get_instance_storage(self).set(&INSTANCE_TTL, instance_ttl);
+    }
+
+    fn extend_instance_ttl(&self) {
+        let (threshold, extend_to) = self.get_instance_ttl();
+        let extend_to = extend_to.min(self.storage().max_ttl());
+        get_instance_storage(self).extend_ttl(threshold.min(extend_to), extend_to);
     }
 
     fn panic_if_not_admin(&self) {
@@ -100,7 +737,9 @@ impl EnvExtensions for Env {
         if admin.is_none() {
             panic_with_error!(self, Error::Unauthorized);
         }
-        admin.unwrap().require_auth()
+        admin.unwrap().require_auth();
+        // admin operations keep the contract configuration alive
+        self.extend_instance_ttl();
     }
 }
 
@@ -111,3 +750,75 @@ fn get_instance_storage(e: &Env) -> Instance {
 fn get_persistent_storage(e: &Env) -> Persistent {
     e.storage().persistent()
 }
+
+fn get_temporary_storage(e: &Env) -> Temporary {
+    e.storage().temporary()
+}
+
+// Decodes the subscription, filling in the defaults for fields missing in older storage formats
+fn upgrade_subscription(e: &Env, fields: &Map<Symbol, Val>) -> Subscription {
+    let field = |name: &str| fields.get(Symbol::new(e, name));
+    let version: u32 = field("format_version")
+        .map(|version| u32::try_from_val(e, &version).unwrap())
+        .unwrap_or(0);
+    if version == SUBSCRIPTION_FORMAT_VERSION {
+        return Subscription::try_from_val(e, &fields.to_val()).unwrap();
+    }
+    let required = |name: &str| field(name).unwrap();
+    let optional = |name: &str| field(name).map(|value| u32::try_from_val(e, &value).unwrap());
+    let threshold = u32::try_from_val(e, &required("threshold")).unwrap();
+    Subscription {
+        owner: Address::try_from_val(e, &required("owner")).unwrap(),
+        base: TryFromVal::try_from_val(e, &required("base")).unwrap(),
+        quote: TryFromVal::try_from_val(e, &required("quote")).unwrap(),
+        threshold,
+        threshold_up: optional("threshold_up").unwrap_or(threshold),
+        threshold_down: optional("threshold_down").unwrap_or(threshold),
+        heartbeat: u32::try_from_val(e, &required("heartbeat")).unwrap(),
+        webhook: TryFromVal::try_from_val(e, &required("webhook")).unwrap(),
+        balance: u64::try_from_val(e, &required("balance")).unwrap(),
+        status: TryFromVal::try_from_val(e, &required("status")).unwrap(),
+        updated: u64::try_from_val(e, &required("updated")).unwrap(),
+        last_deposit: field("last_deposit")
+            .map(|value| u64::try_from_val(e, &value).unwrap())
+            .unwrap_or(0),
+        priority: optional("priority").unwrap_or(0),
+        live_until: optional("live_until").unwrap_or(0),
+        charge_interval_ms: field("charge_interval_ms")
+            .map(|value| u64::try_from_val(e, &value).unwrap())
+            .unwrap_or(DAY),
+        webhook_expires_at: field("webhook_expires_at")
+            .and_then(|value| Option::<u64>::try_from_val(e, &value).unwrap()),
+        last_deposit_seq: field("last_deposit_seq")
+            .map(|value| u64::try_from_val(e, &value).unwrap())
+            .unwrap_or(0),
+        max_acceptable_fee: field("max_acceptable_fee")
+            .and_then(|value| Option::<u64>::try_from_val(e, &value).unwrap()),
+        warned: field("warned")
+            .map(|value| bool::try_from_val(e, &value).unwrap())
+            .unwrap_or(false),
+        token: field("token")
+            .map(|value| Address::try_from_val(e, &value).unwrap())
+            .unwrap_or_else(|| e.get_token()),
+        grace_until: field("grace_until")
+            .and_then(|value| Option::<u64>::try_from_val(e, &value).unwrap()),
+        label: field("label")
+            .map(|value| Bytes::try_from_val(e, &value).unwrap())
+            .unwrap_or_else(|| Bytes::new(e)),
+        // older subscriptions are counted once refreshed by the next charge
+        tracked_fee: field("tracked_fee")
+            .map(|value| u64::try_from_val(e, &value).unwrap())
+            .unwrap_or(0),
+        // older subscriptions are past the cancellation grace period, so nothing is refundable
+        activation_fee: field("activation_fee")
+            .map(|value| u64::try_from_val(e, &value).unwrap())
+            .unwrap_or(0),
+        activated_at: field("activated_at")
+            .map(|value| u64::try_from_val(e, &value).unwrap())
+            .unwrap_or(0),
+        refund_hold: field("refund_hold")
+            .map(|value| u64::try_from_val(e, &value).unwrap())
+            .unwrap_or(0),
+        format_version: SUBSCRIPTION_FORMAT_VERSION,
+    }
+}
@@ -5,23 +5,90 @@ mod types;
 
 use extensions::env_extensions::EnvExtensions;
 use soroban_sdk::{
-    contract, contractimpl, panic_with_error, symbol_short, token::TokenClient, Address, BytesN, Env, Symbol, Vec
+    contract, contractimpl, panic_with_error, symbol_short, token::TokenClient, vec, Address, Bytes, BytesN, Env, IntoVal, Map, String, Symbol, Val, Vec
 };
 use types::{
-    contract_config::ContractConfig, error::Error, subscription::Subscription,
+    asset::Asset, contract_config::ContractConfig, error::Error,
+    events::{
+        AdjustedEvent, AdminChangedEvent, CancelledEvent, ChargeSkippedEvent, EventAccount, EventSubscription, ChargedEvent, CreatedEvent, DepositedEvent, FeeCapHitEvent, FeeChangedEvent, ForceCancelledEvent,
+        HibernatedEvent, MergedEvent, OwnerChangedEvent, PreSuspendWarningEvent, ResumedEvent, SuspendedEvent, TriggeredEvent, UpdatedEvent, WebhookEvent, WithdrawnEvent, WokenEvent,
+    }, payment_token::PaymentToken, subscription::{Subscription, SUBSCRIPTION_FORMAT_VERSION},
     subscription_init_params::SubscriptionInitParams, subscription_status::SubscriptionStatus,
+    suspend_behavior::SuspendBehavior, ticker_asset::TickerAsset,
 };
 
 const REFLECTOR: Symbol = symbol_short!("reflector");
 
+// Debug event topic for subscriptions skipped by the charge
+const CHARGE_SKIPPED: &str = "charge_skipped";
+
+// Event topic for subscriptions approaching the suspension
+const PRE_SUSPEND_WARNING: &str = "pre_suspend_warning";
+
+// Event topic for subscriptions terminated by the admin
+const FORCE_CANCELLED: &str = "force_cancelled";
+
+// Event topic for subscriptions billed at their owner fee cap
+const FEE_CAP_HIT: &str = "fee_cap_hit";
+
+// Registry method checking whether an owner may create subscriptions
+const IS_ALLOWED: &str = "is_allowed";
+
 // 1 day in milliseconds
 const DAY: u64 = 86400 * 1000;
 
+// Average ledger close time in milliseconds
+const LEDGER_TIME: u64 = 5000;
+
+// Default maximum webhook size in bytes
 const MAX_WEBHOOK_SIZE: u32 = 2048;
 
-// Minimum heartbeat in minutes
+// Default multiple of the subscription fee charged on activation
+const ACTIVATION_FEE_FACTOR: u32 = 2;
+
+// Maximum subscription label size in bytes
+const MAX_LABEL_SIZE: u32 = 64;
+
+// Default minimum heartbeat in minutes
 const MIN_HEARTBEAT: u32 = 5;
 
+// Threshold bounds in percentage
+const MIN_THRESHOLD: u32 = 1;
+const MAX_THRESHOLD: u32 = 10000;
+
+// Maximum number of IDs accepted by batch queries
+const MAX_QUERY_SIZE: u32 = 100;
+
+// Maximum number of subscriptions processed by a single TTL bump
+const MAX_TTL_BUMP_SIZE: u32 = 100;
+
+// Maximum number of subscriptions refunded by a single dust withdrawal
+const MAX_WITHDRAW_SIZE: u32 = 100;
+
+// Maximum number of subscriptions processed by a single charge
+const MAX_CHARGE_SIZE: u32 = 100;
+
+// Maximum number of subscriptions scanned by a single index rebuild
+const MAX_REBUILD_SIZE: u32 = 100;
+
+// Period after the activation within which the cancellation refunds a part of the activation fee, in milliseconds
+const CANCEL_GRACE_PERIOD: u64 = 3600 * 1000;
+
+// Maximum number of subscriptions held by a single owner, bounding the owner index size
+const MAX_OWNER_SUBSCRIPTIONS: u32 = 100;
+
+// Maximum number of subscriptions created by a single batch
+const MAX_CREATE_SIZE: u32 = 20;
+
+// Maximum number of subscriptions funded by a single batch deposit
+const MAX_DEPOSIT_SIZE: u32 = 20;
+
+// Number of base fee changes kept for billing elapsed intervals
+const FEE_HISTORY_SIZE: u32 = 30;
+
+// Number of recent triggers kept in storage
+const TRIGGER_HISTORY_SIZE: u32 = 10;
+
 #[contract]
 pub struct SubscriptionContract;
 
@@ -38,6 +105,8 @@ impl SubscriptionContract {
     // # Panics
     //
     // Panics if the contract is already initialized
+    // Panics if the fee is zero
+    // Panics if the minimum heartbeat is zero
     pub fn config(e: Env, config: ContractConfig) {
         config.admin.require_auth();
         if e.is_initialized() {
@@ -45,386 +114,3309 @@ impl SubscriptionContract {
         }
 
         e.set_admin(&config.admin);
-        e.set_fee(config.fee);
+        set_fee(&e, config.fee);
         e.set_token(&config.token);
+        set_min_heartbeat(&e, config.min_heartbeat.unwrap_or(MIN_HEARTBEAT));
         e.set_last_subscription_id(0);
+        e.extend_instance_ttl();
     }
 
-    // Sets the base fee for the contract. Can be invoked only by the admin account.
+    // Proposes the new admin, who has to accept the transfer to take over. Can be invoked only by the admin account.
     //
     // # Arguments
     //
-    // * `fee` - New base fee
+    // * `new_admin` - Proposed admin address
     //
     // # Panics
     //
     // Panics if the caller doesn't match admin address
-    pub fn set_fee(e: Env, fee: u64) {
+    pub fn propose_admin(e: Env, new_admin: Address) {
         e.panic_if_not_admin();
-        e.set_fee(fee);
+        e.set_pending_admin(&Some(new_admin));
     }
 
-    // Triggers the subscription. Can be invoked only by the admin account.
+    // Accepts the proposed admin transfer. Can be invoked only by the proposed admin account.
+    //
+    // # Panics
+    //
+    // Panics if no admin transfer has been proposed
+    // Panics if the caller doesn't match the proposed admin address
+    pub fn accept_admin(e: Env) {
+        let admin = e
+            .get_pending_admin()
+            .unwrap_or_else(|| panic_with_error!(e, Error::NoPendingAdmin));
+        admin.require_auth();
+        let previous_admin = e.get_admin().unwrap();
+        e.set_admin(&admin);
+        e.set_pending_admin(&None);
+        e.events().publish(
+            (REFLECTOR, symbol_short!("admin")),
+            AdminChangedEvent { previous_admin, admin },
+        );
+    }
+
+    // Sets the base fee for the contract. Can be invoked only by the admin account.
     //
     // # Arguments
     //
-    // * `timestamp` - Timestamp of the trigger
-    // * `trigger_hash` - Hash of the trigger data
+    // * `fee` - New base fee
     //
     // # Panics
     //
     // Panics if the caller doesn't match admin address
-    pub fn trigger(e: Env, timestamp: u64, trigger_hash: BytesN<32>) {
+    // Panics if the fee is zero
+    pub fn set_fee(e: Env, fee: u64) {
         e.panic_if_not_admin();
+        let previous_fee = e.get_fee();
+        set_fee(&e, fee);
         e.events().publish(
-            (REFLECTOR, symbol_short!("triggered")),
-            (timestamp, trigger_hash),
+            (REFLECTOR, symbol_short!("fee")),
+            FeeChangedEvent { previous_fee, fee },
         );
     }
 
-    // Updates the contract source code. Can be invoked only by the admin account.
+    // Sets the webhook storage surcharge, charged per 1024 bytes of webhook on top of the base fee. Can be invoked only by the admin account.
     //
     // # Arguments
     //
-    // * `admin` - Admin account address
-    // * `wasm_hash` - WASM hash of the contract source code
+    // * `webhook_byte_fee` - New surcharge per 1024 webhook bytes
     //
     // # Panics
     //
     // Panics if the caller doesn't match admin address
-    pub fn update_contract(e: Env, wasm_hash: BytesN<32>) {
+    pub fn set_webhook_byte_fee(e: Env, webhook_byte_fee: u64) {
         e.panic_if_not_admin();
-        e.deployer().update_current_contract_wasm(wasm_hash)
+        e.set_webhook_byte_fee(webhook_byte_fee);
     }
 
-    // Withdraws funds from the contract, and updates balance of subscriptions. Can be invoked only by the admin account.
+    // Sets the registry contract gating subscription creation. Can be invoked only by the admin account.
     //
     // # Arguments
     //
-    // * `subscription_ids` - Subscription ID
+    // * `registry` - Registry contract address, or None to disable the check
     //
     // # Panics
     //
     // Panics if the caller doesn't match admin address
-    pub fn charge(e: Env, subscription_ids: Vec<u64>) {
+    pub fn set_registry(e: Env, registry: Option<Address>) {
         e.panic_if_not_admin();
-        let mut total_charge: u64 = 0;
-        let now = now(&e);
-        for subscription_id in subscription_ids.iter() {
-            if let Some(mut subscription) = e.get_subscription(subscription_id) {
-                let days = (now - subscription.updated) / DAY;
-                if days == 0 {
-                    continue;
-                }
-                let fee = calc_fee(&e, &subscription.heartbeat, &subscription.threshold);
-                let mut charge = days * fee;
-                if subscription.balance < charge {
-                    charge = subscription.balance;
-                }
-                subscription.balance -= charge;
-                subscription.updated = now;
-                if subscription.balance < fee {
-                    // Deactivate the subscription if the balance is less than the fee
-                    subscription.status = SubscriptionStatus::Suspended;
-                    e.events().publish(
-                        (
-                            REFLECTOR,
-                            symbol_short!("suspended"),
-                            subscription.owner.clone(),
-                        ),
-                        (now, subscription_id),
-                    );
-                }
-                e.set_subscription(subscription_id, &subscription);
-
-                e.events().publish(
-                    (
-                        REFLECTOR,
-                        symbol_short!("charged"),
-                        subscription.owner,
-                    ),
-                    (now, subscription_id, charge),
-                );
-
-                total_charge += charge;
-            }
-        }
-        // If there is nothing to charge, return
-        if total_charge == 0 {
-            return;
-        }
-
-        //Burn the tokens
-        get_token_client(&e).burn(&e.current_contract_address(), &(total_charge as i128));
+        e.set_registry(&registry);
     }
 
-    // Public
-
-    // Creates a new subscription.
+    // Sets the account allowed to charge subscriptions besides the admin. Can be invoked only by the admin account.
     //
     // # Arguments
     //
-    // * `new_subscription` - Subscription data
-    // * `amount` - Initial deposit amount
+    // * `charger` - Charger address, or None to leave charging to the admin only
     //
-    // # Returns
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_charger(e: Env, charger: Option<Address>) {
+        e.panic_if_not_admin();
+        e.set_charger(&charger);
+    }
+
+    // Sets the default charge interval of subscriptions with the base asset. Can be invoked only by the admin account.
+    //
+    // # Arguments
     //
-    // Subscription ID
+    // * `asset` - Base asset
+    // * `interval_ms` - Charge interval in milliseconds
     //
     // # Panics
     //
-    // Panics if the contract is not initialized
-    // Panics if the amount is less than the base fee
-    // Panics if the caller doesn't match the owner address
-    // Panics if the token transfer fails
-    // Panics if the subscription is invalid
-    pub fn create_subscription(
-        e: Env,
-        new_subscription: SubscriptionInitParams,
-        amount: u64,
-    ) -> (u64, Subscription) {
-        panic_if_not_initialized(&e);
-        // Check the authorization
-        new_subscription.owner.require_auth();
-
-        let subscription_fee = calc_fee(&e, &new_subscription.heartbeat, &new_subscription.threshold);
-
-        // Check the amount
-        let init_fee = subscription_fee * 2; // init fee is 2 times the subscription fee
-        if amount < init_fee {
-            e.panic_with_error(Error::InvalidAmount);
-        }
-
-        if MIN_HEARTBEAT > new_subscription.heartbeat {
-            e.panic_with_error(Error::InvalidHeartbeat);
-        }
-
-        if new_subscription.threshold == 0 || new_subscription.threshold > 10000 {
-            e.panic_with_error(Error::InvalidThreshold);
-        }
-
-        if new_subscription.webhook.len() > MAX_WEBHOOK_SIZE {
-            e.panic_with_error(Error::WebhookTooLong);
-        }
-
-        // Transfer and burn the tokens
-        transfer_tokens_to_current_contract(&e, &new_subscription.owner, amount, init_fee);
-
-        //todo: check if the subscription is valid and the amount is enough
-        let subscription_id = e.get_last_subscription_id() + 1;
-        let subscription = Subscription {
-            owner: new_subscription.owner,
-            base: new_subscription.base,
-            quote: new_subscription.quote,
-            threshold: new_subscription.threshold,
-            heartbeat: new_subscription.heartbeat,
-            webhook: new_subscription.webhook,
-            balance: amount - init_fee,
-            status: SubscriptionStatus::Active,
-            updated: now(&e), // normalize to milliseconds
-        };
-        e.set_subscription(subscription_id, &subscription);
-        e.set_last_subscription_id(subscription_id);
-        
-        e.extend_subscription_ttl(subscription_id, calc_ledgers_to_live(&e, &subscription_fee, &subscription.balance));
-        let data = (subscription_id, subscription.clone());
-        e.events()
-            .publish((REFLECTOR, symbol_short!("created"), subscription.owner), data.clone());
-        return data;
+    // Panics if the caller doesn't match admin address
+    pub fn set_asset_interval(e: Env, asset: Asset, interval_ms: u64) {
+        e.panic_if_not_admin();
+        e.set_asset_interval(&asset, interval_ms);
     }
 
-    // Deposits funds to the subscription.
+    // Allows or disallows paying new subscriptions in the token. Can be invoked only by the admin account.
+    // The fees are charged in the units of the contract token, so only tokens of the same value should be allowed.
+    // The contract token is always allowed, and the subscriptions paid in a disallowed token keep their token.
     //
     // # Arguments
     //
-    // * `from` - Sender address
-    // * `subscription_id` - Subscription ID
-    // * `amount` - Amount to deposit
+    // * `token` - Token address
+    // * `allowed` - Whether the token is allowed
     //
     // # Panics
     //
-    // Panics if the contract is not initialized
-    // Panics if the amount is zero
-    // Panics if the subscription does not exist
-    // Panics if the token transfer fails
-    pub fn deposit(e: Env, from: Address, subscription_id: u64, amount: u64) {
-        panic_if_not_initialized(&e);
-        from.require_auth();
-        if amount == 0 {
-            e.panic_with_error(Error::InvalidAmount);
-        }
-        let mut subscription = e
-            .get_subscription(subscription_id)
-            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
-        let mut burn_amount = 0;
-
-        let subscription_fee = calc_fee(&e, &subscription.heartbeat, &subscription.threshold);
-
-        match subscription.status {
-            SubscriptionStatus::Suspended => {
-                // Check if the subscription is suspended
-                if amount < subscription_fee {
-                    e.panic_with_error(Error::InvalidAmount);
-                }
-                // Set the activation fee as the burn amount
-                burn_amount = subscription_fee;
-                subscription.status = SubscriptionStatus::Active;
-            },
-            _ => {}
-        }
-
-        // Transfer and burn the tokens
-        transfer_tokens_to_current_contract(&e, &from, amount, burn_amount);
-
-        subscription.balance += amount - burn_amount;
-        e.set_subscription(subscription_id, &subscription);
-        e.extend_subscription_ttl(subscription_id, calc_ledgers_to_live(&e, &subscription_fee, &subscription.balance));
-        e.events().publish(
-            (REFLECTOR, symbol_short!("deposited"), subscription.owner.clone()),
-            (subscription_id, subscription, amount),
-        );
+    // Panics if the caller doesn't match admin address
+    pub fn set_token_allowed(e: Env, token: Address, allowed: bool) {
+        e.panic_if_not_admin();
+        e.set_token_allowed(&token, allowed);
     }
 
-    // Withdraws funds from the subscription and deactivates it.
+    // Sets the maximum number of subscriptions that can reference the price source. Can be invoked only by the admin account.
     //
     // # Arguments
     //
-    // * `subscription_id` - Subscription ID
-    // # Panics if the contract is not initialized
-    // # Panics if the subscription does not exist
-    // # Panics if the caller doesn't match the owner address
-    // # Panics if the subscription is not active
-    // # Panics if the token transfer fails
-    pub fn cancel(e: Env, subscription_id: u64) {
-        panic_if_not_initialized(&e);
-        let subscription = e
-            .get_subscription(subscription_id)
-            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
-        subscription.owner.require_auth();
-        match subscription.status {
-            SubscriptionStatus::Active => {}
-            _ => {
-                e.panic_with_error(Error::InvalidSubscriptionStatusError);
-            }
-        }
-        // Transfer the remaining balance to the owner
-        transfer_tokens(
-            &e,
-            &e.current_contract_address(),
-            &subscription.owner,
-            subscription.balance,
-        );
-        e.remove_subscription(subscription_id);
-        e.events()
-            .publish((REFLECTOR, symbol_short!("cancelled"), subscription.owner), subscription_id);
+    // * `source` - Price source
+    // * `cap` - Maximum number of subscriptions
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_source_cap(e: Env, source: String, cap: u32) {
+        e.panic_if_not_admin();
+        e.set_source_cap(&source, cap);
     }
 
-    // Gets the subscription by ID.
+    // Sets the display symbol of the asset. Can be invoked only by the admin account.
     //
     // # Arguments
     //
-    // * `subscription_id` - Subscription ID
+    // * `asset` - Asset
+    // * `symbol` - Display symbol
     //
-    // # Returns
+    // # Panics
     //
-    // Subscription data
+    // Panics if the caller doesn't match admin address
+    pub fn set_asset_symbol(e: Env, asset: Asset, symbol: Symbol) {
+        e.panic_if_not_admin();
+        e.set_asset_symbol(&asset, &symbol);
+    }
+
+    // Sets the maximum priority a subscription can be created with. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `max_priority` - New maximum priority
     //
     // # Panics
     //
-    // Panics if the contract is not initialized
-    pub fn get_subscription(e: Env, subscription_id: u64) -> Subscription {
-        panic_if_not_initialized(&e);
-        e.get_subscription(subscription_id)
-            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound))
+    // Panics if the caller doesn't match admin address
+    pub fn set_max_priority(e: Env, max_priority: u32) {
+        e.panic_if_not_admin();
+        e.set_max_priority(max_priority);
     }
 
-    // Gets the last subscription ID.
+    // Sets the maximum total balance of all subscriptions the contract will custody. Can be invoked only by the admin account.
     //
-    // # Returns
-    // Last subscription ID
-    pub fn last_id(e: Env) -> u64 {
-        panic_if_not_initialized(&e);
-        e.get_last_subscription_id()
+    // # Arguments
+    //
+    // * `max_tvl` - New total balance cap, or None to remove the cap
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_max_tvl(e: Env, max_tvl: Option<u64>) {
+        e.panic_if_not_admin();
+        e.set_max_tvl(&max_tvl);
     }
 
-    // Returns admin address of the contract.
+    // Sets whether partially elapsed intervals are charged proportionally. Can be invoked only by the admin account.
     //
-    // # Returns
+    // # Arguments
     //
-    // Contract admin account address
-    pub fn admin(e: Env) -> Option<Address> {
-        e.get_admin()
+    // * `prorate_charge` - True to charge elapsed time proportionally, false to charge whole intervals only
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_prorate_charge(e: Env, prorate_charge: bool) {
+        e.panic_if_not_admin();
+        e.set_prorate_charge(prorate_charge);
     }
 
-    // Returns current protocol version of the contract.
+    // Sets the time an owner has to wait before recreating a cancelled subscription for the same pair. Can be invoked only by the admin account.
     //
-    // # Returns
+    // # Arguments
     //
-    // Contract protocol version
-    pub fn version(_e: Env) -> u32 {
-        env!("CARGO_PKG_VERSION")
-            .split(".")
-            .next()
-            .unwrap()
-            .parse::<u32>()
-            .unwrap()
+    // * `recreate_cooldown` - New recreate cooldown in milliseconds
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_recreate_cooldown(e: Env, recreate_cooldown: u64) {
+        e.panic_if_not_admin();
+        e.set_recreate_cooldown(recreate_cooldown);
     }
 
-    // Returns the base fee of the contract.
+    // Sets the minimum time between deposits to the same subscription. Can be invoked only by the admin account.
     //
-    // # Returns
+    // # Arguments
     //
-    // Base fee
-    pub fn fee(e: Env) -> u64 {
-        panic_if_not_initialized(&e);
-        e.get_fee()
+    // * `min_deposit_interval` - New minimum deposit interval in milliseconds
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_min_deposit_interval(e: Env, min_deposit_interval: u64) {
+        e.panic_if_not_admin();
+        e.set_min_deposit_interval(min_deposit_interval);
     }
 
-    // Returns the token address of the contract.
+    // Triggers the subscription. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `timestamp` - Timestamp of the trigger
+    // * `trigger_hash` - Hash of the trigger data
     //
     // # Returns
     //
-    // Token address
-    pub fn token(e: Env) -> Address {
-        panic_if_not_initialized(&e);
-        e.get_token()
+    // Stored timestamp and hash of the trigger
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn trigger(e: Env, timestamp: u64, trigger_hash: BytesN<32>) -> (u64, BytesN<32>) {
+        e.panic_if_not_admin();
+        e.set_last_trigger(timestamp, &trigger_hash);
+        let mut trigger_history = e.get_trigger_history();
+        if trigger_history.len() >= TRIGGER_HISTORY_SIZE {
+            trigger_history.pop_front();
+        }
+        trigger_history.push_back((timestamp, trigger_hash.clone()));
+        e.set_trigger_history(&trigger_history);
+        e.events().publish(
+            (REFLECTOR, symbol_short!("triggered")),
+            TriggeredEvent { timestamp, trigger_hash },
+        );
+        e.get_last_trigger().unwrap()
     }
-}
 
-fn panic_if_not_initialized(e: &Env) {
-    if !e.is_initialized() {
-        panic_with_error!(e, Error::NotInitialized);
+    // Updates the contract source code. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `admin` - Admin account address
+    // * `wasm_hash` - WASM hash of the contract source code
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn update_contract(e: Env, wasm_hash: BytesN<32>) {
+        e.panic_if_not_admin();
+        e.deployer().update_current_contract_wasm(wasm_hash)
     }
-}
 
-fn get_token_client(e: &Env) -> TokenClient {
-    TokenClient::new(e, &e.get_token())
-}
-
-fn transfer_tokens_to_current_contract(e: &Env, from: &Address, amount: u64, burn_amount: u64) {
-    transfer_tokens(e, from, &e.current_contract_address(), amount);
-    if burn_amount > 0 {
-        let token_client = get_token_client(e);
-        token_client.burn(&e.current_contract_address(), &(burn_amount as i128));
-    }
-}
+    // Sets the bounds of the per-subscription charge interval. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `min_charge_interval` - Minimum charge interval in milliseconds
+    // * `max_charge_interval` - Maximum charge interval in milliseconds
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the bounds are empty or the minimum is zero
+    pub fn set_charge_interval_bounds(e: Env, min_charge_interval: u64, max_charge_interval: u64) {
+        e.panic_if_not_admin();
+        if min_charge_interval == 0 || min_charge_interval > max_charge_interval {
+            e.panic_with_error(Error::InvalidChargeInterval);
+        }
+        e.set_charge_interval_bounds(&(min_charge_interval, max_charge_interval));
+    }
+
+    // Enables or disables the debug events, like the events for subscriptions skipped by the charge. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `debug_events` - True to emit the debug events
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_debug_events(e: Env, debug_events: bool) {
+        e.panic_if_not_admin();
+        e.set_debug_events(debug_events);
+    }
+
+    // Enables or disables the minimal events, omitting owners, depositors and subscription data from the events. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `minimal_events` - True to emit only IDs and amounts
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_minimal_events(e: Env, minimal_events: bool) {
+        e.panic_if_not_admin();
+        e.set_minimal_events(minimal_events);
+    }
+
+    // Sets the reserve the contract keeps on top of the subscription balances when burning charged tokens. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `min_reserve` - New minimum reserve
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_min_reserve(e: Env, min_reserve: u64) {
+        e.panic_if_not_admin();
+        e.set_min_reserve(min_reserve);
+    }
+
+    // Sets the multiple of the subscription fee charged on activation of new and reactivated subscriptions. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `factor` - New activation fee factor
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the factor is zero
+    pub fn set_fee_factor(e: Env, factor: u32) {
+        e.panic_if_not_admin();
+        if factor == 0 {
+            e.panic_with_error(Error::InvalidFee);
+        }
+        e.set_fee_factor(factor);
+    }
+
+    // Sets the percentage of the activation fee refunded when the subscription is cancelled within the grace period, the refunded share is held back from the burn on creation. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `percent` - Refunded percentage, 0 disables the refund
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the percentage exceeds 100
+    pub fn set_activation_refund_percent(e: Env, percent: u32) {
+        e.panic_if_not_admin();
+        if percent > 100 {
+            e.panic_with_error(Error::InvalidAmount);
+        }
+        e.set_activation_refund_percent(percent);
+    }
+
+    // Sets how many days before the projected suspension the owner is warned by the charge. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `pre_suspend_lead_days` - Lead time in days, 0 disables the warnings
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_pre_suspend_lead_days(e: Env, pre_suspend_lead_days: u32) {
+        e.panic_if_not_admin();
+        e.set_pre_suspend_lead_days(pre_suspend_lead_days);
+    }
+
+    // Sets how long underfunded subscriptions stay active before the charge suspends them. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `suspend_grace` - Grace period in milliseconds, 0 suspends underfunded subscriptions immediately
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_suspend_grace(e: Env, suspend_grace: u64) {
+        e.panic_if_not_admin();
+        e.set_suspend_grace(suspend_grace);
+    }
+
+    // Sets what happens to the remaining balance of subscriptions suspended by the charge. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `suspend_behavior` - New suspension behavior
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_suspend_behavior(e: Env, suspend_behavior: SuspendBehavior) {
+        e.panic_if_not_admin();
+        e.set_suspend_behavior(&suspend_behavior);
+    }
+
+    // Sets the minimum heartbeat of new and updated subscriptions. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `minutes` - New minimum heartbeat in minutes
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the minimum heartbeat is zero
+    pub fn set_min_heartbeat(e: Env, minutes: u32) {
+        e.panic_if_not_admin();
+        set_min_heartbeat(&e, minutes);
+    }
+
+    // Sets the maximum webhook size of new and updated subscriptions. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `size` - New maximum webhook size in bytes
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_max_webhook_size(e: Env, size: u32) {
+        e.panic_if_not_admin();
+        e.set_max_webhook_size(size);
+    }
+
+    // Sets the maximum number of subscriptions a single owner may hold. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `limit` - Maximum number of subscriptions, 0 for no limit beyond the owner index bound
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_max_per_owner(e: Env, limit: u32) {
+        e.panic_if_not_admin();
+        e.set_max_per_owner(limit);
+    }
+
+    // Sets the instance storage TTL threshold and extension, applied by admin operations and charges. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `threshold` - TTL in ledgers below which the instance storage is extended
+    // * `extend_to` - TTL in ledgers the instance storage is extended to
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the threshold exceeds the extension or the extension exceeds the maximum TTL
+    pub fn set_instance_ttl(e: Env, threshold: u32, extend_to: u32) {
+        e.panic_if_not_admin();
+        if let Err(error) = validate_instance_ttl(&e, threshold, extend_to) {
+            e.panic_with_error(error);
+        }
+        e.set_instance_ttl(&(threshold, extend_to));
+        e.extend_instance_ttl();
+    }
+
+    // Pauses charging of subscriptions, other operations stay available. Can be invoked only by the admin account.
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn pause_billing(e: Env) {
+        e.panic_if_not_admin();
+        e.set_billing_paused(true);
+    }
+
+    // Resumes charging of subscriptions. Can be invoked only by the admin account.
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn resume_billing(e: Env) {
+        e.panic_if_not_admin();
+        e.set_billing_paused(false);
+    }
+
+    // Pauses or resumes creation, funding and charging of subscriptions. Reads and cancellation stay available. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `paused` - True to pause the contract
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn set_paused(e: Env, paused: bool) {
+        e.panic_if_not_admin();
+        e.set_paused(paused);
+    }
+
+    // Reconstructs the owner index for subscriptions in the ID range starting at the cursor. Can be invoked only by the admin account.
+    // Stale and duplicate entries are dropped from the indexes of the scanned owners, missing entries are restored.
+    // Safe to repeat, the scan can be resumed from the returned cursor.
+    //
+    // # Arguments
+    //
+    // * `cursor` - First subscription ID to scan
+    // * `limit` - Maximum number of IDs to scan, capped at 100
+    //
+    // # Returns
+    //
+    // Next subscription ID to scan, or 0 if all subscriptions were scanned
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn rebuild_indexes(e: Env, cursor: u64, limit: u32) -> u64 {
+        e.panic_if_not_admin();
+        let last_id = e.get_last_subscription_id();
+        let start = cursor.max(1);
+        let end = start.saturating_add(limit.min(MAX_REBUILD_SIZE) as u64).min(last_id + 1);
+        for subscription_id in start..end {
+            if let Some(subscription) = e.get_subscription(subscription_id) {
+                rebuild_owner_index(&e, subscription_id, &subscription);
+            }
+        }
+        if end > last_id {
+            0
+        } else {
+            end
+        }
+    }
+
+    // Withdraws funds from the contract, and updates balance of subscriptions. Can be invoked only by the admin or the charger account.
+    // Higher priority subscriptions are processed first, the rest of the batch beyond the limit is left for the next call.
+    // The charge deliberately doesn't extend the TTL of the subscription entries, it's extended on creation, deposits and `bump_owner_ttl`,
+    // so the billing run cost doesn't depend on the entries being close to the expiration.
+    //
+    // # Arguments
+    //
+    // * `caller` - Admin or charger address
+    // * `subscription_ids` - Subscription ID
+    //
+    // # Returns
+    //
+    // Subscription ID, charged amount and resulting status of each charged subscription, missing and not due subscriptions are omitted
+    //
+    // # Panics
+    //
+    // Panics if the caller is neither the admin nor the charger
+    // Panics if billing is paused
+    // Panics if the contract is paused
+    pub fn charge(e: Env, caller: Address, subscription_ids: Vec<u64>) -> Vec<(u64, u64, SubscriptionStatus)> {
+        panic_if_cannot_charge(&e, &caller);
+        charge_subscriptions(&e, &subscription_ids)
+    }
+
+    // Charges the due active subscriptions within the ID range last updated within the time window, allowing to split the billing run into time slices.
+    // Can be invoked only by the admin or the charger account.
+    //
+    // # Arguments
+    //
+    // * `caller` - Admin or charger address
+    // * `window_start` - Window start timestamp in milliseconds, inclusive
+    // * `window_end` - Window end timestamp in milliseconds, exclusive
+    // * `start_id` - First subscription ID to inspect
+    // * `limit` - Number of IDs to inspect, capped at 100
+    //
+    // # Returns
+    //
+    // Number of charged subscriptions
+    //
+    // # Panics
+    //
+    // Panics if the caller is neither the admin nor the charger
+    // Panics if billing is paused
+    // Panics if the contract is paused
+    pub fn charge_window(e: Env, caller: Address, window_start: u64, window_end: u64, start_id: u64, limit: u32) -> u32 {
+        panic_if_cannot_charge(&e, &caller);
+        let start_id = start_id.max(1);
+        let end_id = start_id
+            .saturating_add(limit.min(MAX_CHARGE_SIZE) as u64)
+            .min(e.get_last_subscription_id() + 1);
+        let now = now(&e);
+        let mut subscription_ids = Vec::new(&e);
+        for subscription_id in start_id..end_id {
+            let Some(mut subscription) = e.get_subscription(subscription_id) else {
+                continue;
+            };
+            // Subscriptions that are not due yet would be skipped by the charge
+            if subscription.status == SubscriptionStatus::Active
+                && subscription.updated >= window_start
+                && subscription.updated < window_end
+                && apply_charge(&e, &mut subscription, now).is_some()
+            {
+                subscription_ids.push_back(subscription_id);
+            }
+        }
+        charge_subscriptions(&e, &subscription_ids).len()
+    }
+
+    // Charges active subscriptions within the ID range, suspending the underfunded ones. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `start_id` - First subscription ID to inspect
+    // * `limit` - Number of IDs to inspect, capped at 100
+    //
+    // # Returns
+    //
+    // IDs of the suspended subscriptions
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if billing is paused
+    // Panics if the contract is paused
+    pub fn sweep(e: Env, start_id: u64, limit: u32) -> Vec<u64> {
+        e.panic_if_not_admin();
+        if e.is_billing_paused() {
+            e.panic_with_error(Error::BillingPaused);
+        }
+        panic_if_paused(&e);
+        let start_id = start_id.max(1);
+        let end_id = start_id
+            .saturating_add(limit.min(MAX_CHARGE_SIZE) as u64)
+            .min(e.get_last_subscription_id() + 1);
+        let mut subscription_ids = Vec::new(&e);
+        for subscription_id in start_id..end_id {
+            if get_status(&e, subscription_id) == Some(SubscriptionStatus::Active) {
+                subscription_ids.push_back(subscription_id);
+            }
+        }
+        let mut suspended = Vec::new(&e);
+        for (subscription_id, _, status) in charge_subscriptions(&e, &subscription_ids).iter() {
+            if status == SubscriptionStatus::Suspended {
+                suspended.push_back(subscription_id);
+            }
+        }
+        suspended
+    }
+
+    // Computes the outcome of charging the subscriptions without applying it. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `subscription_ids` - Subscription ID
+    //
+    // # Returns
+    //
+    // Subscription ID, charged amount and resulting status for every subscription the charge would process
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    pub fn charge_dry_run(e: Env, subscription_ids: Vec<u64>) -> Vec<(u64, u64, SubscriptionStatus)> {
+        e.panic_if_not_admin();
+        let now = now(&e);
+        let mut result = Vec::new(&e);
+        for (subscription_id, mut subscription) in prioritize(&e, &subscription_ids, MAX_CHARGE_SIZE).iter() {
+            if let Some(charge) = apply_charge(&e, &mut subscription, now) {
+                result.push_back((subscription_id, charge, subscription.status));
+            }
+        }
+        result
+    }
+
+    // Terminates the subscription in any status, refunding its balance to the given address instead of the owner.
+    // Can be invoked only by the admin account. The admin can seize the balance of any subscription this way,
+    // so owners have to trust the admin to use it only for compliance terminations.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `refund_to` - Address receiving the remaining balance
+    // * `reason` - Termination reason code
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the subscription does not exist
+    // Panics if the token transfer fails
+    pub fn force_cancel(e: Env, subscription_id: u64, refund_to: Address, reason: u32) {
+        e.panic_if_not_admin();
+        let subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        close_subscription(&e, subscription_id, &subscription, &refund_to);
+        publish_event(
+            &e,
+            Symbol::new(&e, FORCE_CANCELLED),
+            subscription.owner,
+            ForceCancelledEvent {
+                subscription_id,
+                refund_to,
+                amount: subscription.balance,
+                reason,
+            },
+        );
+    }
+
+    // Corrects the subscription balance if it still matches the expected one. No tokens are moved. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `balance` - Corrected balance
+    // * `expected_old` - Balance the correction was computed against
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the subscription does not exist
+    // Panics if the current balance doesn't match the expected one
+    // Panics if the total custodied balance would exceed the cap
+    pub fn set_balance_with_proof(e: Env, subscription_id: u64, balance: u64, expected_old: u64) {
+        e.panic_if_not_admin();
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        if let Err(error) = validate_balance_correction(&subscription, expected_old) {
+            e.panic_with_error(error);
+        }
+        correct_balance(&e, subscription_id, &mut subscription, balance);
+    }
+
+    // Credits or debits the subscription balance, clamping it at zero. No tokens are moved. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `delta` - Amount to credit if positive or to debit if negative
+    //
+    // # Returns
+    //
+    // Corrected balance
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the subscription does not exist
+    // Panics if the corrected balance overflows
+    // Panics if the total custodied balance would exceed the cap
+    pub fn admin_adjust_balance(e: Env, subscription_id: u64, delta: i128) -> u64 {
+        e.panic_if_not_admin();
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        let balance = calc_adjusted_balance(subscription.balance, delta)
+            .unwrap_or_else(|| panic_with_error!(e, Error::InvalidAmount));
+        correct_balance(&e, subscription_id, &mut subscription, balance);
+        balance
+    }
+
+    // Creates a new subscription funded by the sponsor instead of the owner. Can be invoked only by the admin account.
+    //
+    // # Arguments
+    //
+    // * `new_subscription` - Subscription data
+    // * `amount` - Initial deposit amount
+    // * `from` - Sponsor address the deposit is transferred from
+    //
+    // # Returns
+    //
+    // Subscription ID, subscription data and the activation fee charged
+    //
+    // # Panics
+    //
+    // Panics if the caller doesn't match admin address
+    // Panics if the sponsor doesn't authorize the transfer
+    // Panics in the same cases as `create_subscription`, except for the owner authorization
+    pub fn create_sponsored(e: Env, new_subscription: SubscriptionInitParams, amount: u64, from: Address) -> (u64, Subscription, u64) {
+        e.panic_if_not_admin();
+        from.require_auth();
+        create_subscription(&e, new_subscription, amount, &from)
+    }
+
+    // Public
+
+    // Creates a new subscription.
+    //
+    // # Arguments
+    //
+    // * `new_subscription` - Subscription data
+    // * `amount` - Initial deposit amount
+    //
+    // # Returns
+    //
+    // Subscription ID, subscription data and the activation fee charged
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the amount is less than the base fee
+    // Panics if the caller doesn't match the owner address
+    // Panics if the token transfer fails
+    // Panics if the subscription is invalid
+    // Panics if the base and quote are the same ticker asset
+    // Panics if the owner is not allowed by the registry
+    // Panics if the owner cancelled a subscription for the same pair within the recreate cooldown
+    // Panics if the charge interval is out of the configured bounds
+    // Panics if the webhook expiration is not in the future
+    // Panics if the total custodied balance would exceed the cap
+    // Panics if the base or quote source is referenced by the maximum number of subscriptions
+    // Panics if the owner holds the maximum number of subscriptions
+    // Panics if the payment token is not allowed by the admin
+    // Panics if the contract is paused
+    pub fn create_subscription(
+        e: Env,
+        new_subscription: SubscriptionInitParams,
+        amount: u64,
+    ) -> (u64, Subscription, u64) {
+        panic_if_not_initialized(&e);
+        // Check the authorization
+        new_subscription.owner.require_auth();
+        let owner = new_subscription.owner.clone();
+        create_subscription(&e, new_subscription, amount, &owner)
+    }
+
+    // Creates multiple subscriptions at once. A failing item reverts the whole batch.
+    //
+    // # Arguments
+    //
+    // * `params` - Subscription data for each subscription
+    // * `amounts` - Initial deposit amount for each subscription
+    //
+    // # Returns
+    //
+    // Subscription ID and data pairs in the order of the parameters
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the number of parameters and amounts differ
+    // Panics if the batch exceeds the batch limit
+    // Panics in the same cases as `create_subscription` for any item
+    pub fn create_subscriptions(e: Env, params: Vec<SubscriptionInitParams>, amounts: Vec<u64>) -> Vec<(u64, Subscription)> {
+        panic_if_not_initialized(&e);
+        if let Err(error) = validate_create_batch(&params, &amounts) {
+            e.panic_with_error(error);
+        }
+        let mut subscriptions = Vec::new(&e);
+        // The authorization can be required only once per address within the invocation
+        let mut authorized: Vec<Address> = Vec::new(&e);
+        // Transferred amounts are accumulated per owner and token, burned amounts per token, to move each with a single call
+        let mut transfers: Map<(Address, Address), u64> = Map::new(&e);
+        let mut burns: Map<Address, u64> = Map::new(&e);
+        for (new_subscription, amount) in params.iter().zip(amounts.iter()) {
+            if !authorized.contains(&new_subscription.owner) {
+                new_subscription.owner.require_auth();
+                authorized.push_back(new_subscription.owner.clone());
+            }
+            let (subscription_id, subscription, init_fee) = open_subscription(&e, new_subscription, amount);
+            let transfer_key = (subscription.owner.clone(), subscription.token.clone());
+            transfers.set(transfer_key.clone(), transfers.get(transfer_key).unwrap_or(0) + amount);
+            let burned = burns.get(subscription.token.clone()).unwrap_or(0);
+            burns.set(subscription.token.clone(), burned + init_fee - subscription.refund_hold);
+            subscriptions.push_back((subscription_id, subscription));
+        }
+        for ((owner, token), transferred) in transfers.iter() {
+            transfer_tokens_to_current_contract(&e, &token, &owner, transferred);
+        }
+        for (token, burned) in burns.iter() {
+            burn_tokens(&e, &token, burned);
+        }
+        subscriptions
+    }
+
+    // Deposits funds to the subscription. Cancelled subscriptions are removed from storage and can't be funded.
+    // Suspended subscriptions are no longer reactivated by a deposit, use `reactivate` instead.
+    //
+    // # Arguments
+    //
+    // * `from` - Sender address
+    // * `subscription_id` - Subscription ID
+    // * `amount` - Amount to deposit
+    //
+    // # Returns
+    //
+    // Credited and burned amounts
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the contract is paused
+    // Panics if the amount is zero
+    // Panics if the subscription does not exist or has been cancelled
+    // Panics if the subscription is suspended
+    // Panics if the minimum deposit interval has not elapsed since the last deposit
+    // Panics if the total custodied balance would exceed the cap
+    // Panics if the token transfer fails
+    pub fn deposit(e: Env, from: Address, subscription_id: u64, amount: u64) -> (u64, u64) {
+        deposit_to_subscription(&e, &from, subscription_id, amount, None)
+    }
+
+    // Deposits funds to the owner's own subscription, guarding against funding a wrong subscription ID.
+    //
+    // # Arguments
+    //
+    // * `owner` - Owner address
+    // * `subscription_id` - Subscription ID
+    // * `amount` - Amount to deposit
+    //
+    // # Returns
+    //
+    // Credited and burned amounts
+    //
+    // # Panics
+    //
+    // Panics if the subscription is not owned by the owner
+    // Panics in the same cases as `deposit`
+    pub fn owner_deposit(e: Env, owner: Address, subscription_id: u64, amount: u64) -> (u64, u64) {
+        deposit_to_subscription(&e, &owner, subscription_id, amount, Some(&owner))
+    }
+
+    // Reactivates the suspended subscription. The activation fee is burned from the amount, the rest is added to the balance.
+    //
+    // # Arguments
+    //
+    // * `from` - Sender address
+    // * `subscription_id` - Subscription ID
+    // * `amount` - Amount to deposit, including the activation fee
+    //
+    // # Returns
+    //
+    // Credited and burned amounts
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the contract is paused
+    // Panics if the subscription does not exist
+    // Panics if the subscription is not suspended
+    // Panics if the amount is less than the activation fee
+    // Panics if the total custodied balance would exceed the cap
+    // Panics if the token transfer fails
+    pub fn reactivate(e: Env, from: Address, subscription_id: u64, amount: u64) -> (u64, u64) {
+        panic_if_not_initialized(&e);
+        panic_if_paused(&e);
+        from.require_auth();
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        if subscription.status != SubscriptionStatus::Suspended {
+            e.panic_with_error(Error::InvalidSubscriptionStatusError);
+        }
+        let activation_fee = resume_subscription(&e, &mut subscription, amount);
+        let owner = subscription.owner.clone();
+        let result = fund_subscription(&e, &from, subscription_id, subscription, amount, activation_fee);
+        publish_event(&e, symbol_short!("resumed"), owner, ResumedEvent { subscription_id, activation_fee });
+        result
+    }
+
+    // Deposits funds to multiple subscriptions with a single transfer per token. Suspended subscriptions are reactivated,
+    // the activation fee is burned from their amount as in `reactivate`.
+    //
+    // # Arguments
+    //
+    // * `from` - Sender address
+    // * `deposits` - Subscription ID and amount pairs
+    //
+    // # Returns
+    //
+    // Credited and burned amounts of each deposit
+    //
+    // # Panics
+    //
+    // Panics if the number of deposits exceeds the batch limit
+    // Panics in the same cases as `deposit` and `reactivate`, the whole batch is reverted
+    pub fn deposit_many(e: Env, from: Address, deposits: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+        panic_if_not_initialized(&e);
+        panic_if_paused(&e);
+        from.require_auth();
+        if deposits.len() > MAX_DEPOSIT_SIZE {
+            e.panic_with_error(Error::BatchTooLarge);
+        }
+        // Transferred and burned amounts are accumulated per token to move each token with a single call
+        let mut token_totals: Map<Address, (u64, u64)> = Map::new(&e);
+        let mut results = Vec::new(&e);
+        for (subscription_id, amount) in deposits.iter() {
+            if amount == 0 {
+                e.panic_with_error(Error::InvalidAmount);
+            }
+            let mut subscription = e
+                .get_subscription(subscription_id)
+                .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+            let resumed = subscription.status == SubscriptionStatus::Suspended;
+            let burn_amount = if resumed {
+                resume_subscription(&e, &mut subscription, amount)
+            } else {
+                if let Err(error) = validate_deposit(&e, &subscription) {
+                    e.panic_with_error(error);
+                }
+                0
+            };
+            let owner = subscription.owner.clone();
+            let (transferred, burned) = token_totals.get(subscription.token.clone()).unwrap_or((0, 0));
+            token_totals.set(subscription.token.clone(), (transferred + amount, burned + burn_amount));
+            results.push_back(credit_subscription(&e, &from, subscription_id, subscription, amount, burn_amount));
+            if resumed {
+                let event = ResumedEvent { subscription_id, activation_fee: burn_amount };
+                publish_event(&e, symbol_short!("resumed"), owner, event);
+            }
+        }
+        for (token, (transferred, burned)) in token_totals.iter() {
+            transfer_tokens_to_current_contract(&e, &token, &from, transferred);
+            burn_tokens(&e, &token, burned);
+        }
+        results
+    }
+
+    // Stops billing of the active subscription, keeping its balance for later.
+    // The time elapsed since the last charge is billed first, including the partially elapsed interval.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the subscription is not active
+    pub fn hibernate(e: Env, subscription_id: u64) {
+        panic_if_not_initialized(&e);
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.owner.require_auth();
+        if subscription.status != SubscriptionStatus::Active {
+            e.panic_with_error(Error::InvalidSubscriptionStatusError);
+        }
+        let now = now(&e);
+        let balance = subscription.balance;
+        // Otherwise hibernating before each interval ends would avoid the billing, as the wake moves the anchor
+        let charge = settle_charge(&e, &mut subscription, now);
+        subscription.status = SubscriptionStatus::Hibernated;
+        e.set_subscription(subscription_id, &subscription);
+        untrack_active(&e, &subscription);
+        untrack_balance(&e, &subscription.token, SubscriptionStatus::Active, balance);
+        if charge > 0 {
+            decrease_tvl(&e, &subscription.token, charge);
+            burn_tokens(&e, &subscription.token, calc_burn_amount(&e, &subscription.token, charge));
+            let event = ChargedEvent {
+                timestamp: now,
+                subscription_id,
+                amount: charge,
+                event_seq: next_event_seq(&e),
+            };
+            publish_event(&e, symbol_short!("charged"), subscription.owner.clone(), event);
+        }
+        let event = HibernatedEvent { timestamp: now, subscription_id };
+        publish_event(&e, symbol_short!("hibernate"), subscription.owner, event);
+    }
+
+    // Resumes billing of the hibernated subscription, starting from the current time.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the subscription is not hibernated
+    pub fn wake(e: Env, subscription_id: u64) {
+        panic_if_not_initialized(&e);
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.owner.require_auth();
+        if subscription.status != SubscriptionStatus::Hibernated {
+            e.panic_with_error(Error::InvalidSubscriptionStatusError);
+        }
+        let now = now(&e);
+        subscription.status = SubscriptionStatus::Active;
+        subscription.updated = now;
+        track_active(&e, &mut subscription);
+        e.set_subscription(subscription_id, &subscription);
+        track_balance(&e, &subscription.token, SubscriptionStatus::Active, subscription.balance);
+        publish_event(&e, symbol_short!("woken"), subscription.owner, WokenEvent { timestamp: now, subscription_id });
+    }
+
+    // Sets the maximum daily fee the owner accepts to be billed. Intervals priced above the cap are billed at the cap,
+    // the subscription keeps running until the owner raises the cap or cancels it.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `max_acceptable_fee` - Maximum daily fee, or None to accept any fee
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    pub fn set_max_acceptable_fee(e: Env, subscription_id: u64, max_acceptable_fee: Option<u64>) {
+        panic_if_not_initialized(&e);
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.owner.require_auth();
+        let active = subscription.status == SubscriptionStatus::Active;
+        if active {
+            untrack_revenue(&e, &subscription);
+        }
+        subscription.max_acceptable_fee = max_acceptable_fee;
+        if active {
+            track_revenue(&e, &mut subscription);
+        }
+        e.set_subscription(subscription_id, &subscription);
+    }
+
+    // Replaces the subscription webhook, renewing its expiration.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `webhook` - New webhook
+    // * `webhook_expires_at` - New webhook expiration timestamp in milliseconds, or None if it doesn't expire
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription has been cancelled
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the webhook is too long
+    // Panics if the webhook expiration is not in the future
+    pub fn set_webhook(e: Env, subscription_id: u64, webhook: Bytes, webhook_expires_at: Option<u64>) {
+        let mut subscription = update_owner_webhook(&e, subscription_id, webhook);
+        if !is_valid_webhook_expiration(&e, webhook_expires_at) {
+            e.panic_with_error(Error::InvalidWebhookExpiration);
+        }
+        subscription.webhook_expires_at = webhook_expires_at;
+        e.set_subscription(subscription_id, &subscription);
+        let event = WebhookEvent {
+            subscription_id,
+            subscription: event_subscription(&e, &subscription),
+        };
+        publish_event(&e, symbol_short!("webhook"), subscription.owner, event);
+    }
+
+    // Replaces the subscription webhook, keeping its expiration and balance.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `webhook` - New webhook
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription has been cancelled
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the webhook is too long
+    pub fn update_webhook(e: Env, subscription_id: u64, webhook: Bytes) {
+        let subscription = update_owner_webhook(&e, subscription_id, webhook);
+        e.set_subscription(subscription_id, &subscription);
+        publish_event(&e, symbol_short!("updated"), subscription.owner, UpdatedEvent { subscription_id });
+    }
+
+    // Replaces the subscription label. The label is only stored for the owner's bookkeeping.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `label` - New label, empty to clear it
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the label is too long
+    pub fn update_label(e: Env, subscription_id: u64, label: Bytes) {
+        panic_if_not_initialized(&e);
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.owner.require_auth();
+        if !is_valid_label(&label) {
+            e.panic_with_error(Error::LabelTooLong);
+        }
+        subscription.label = label;
+        e.set_subscription(subscription_id, &subscription);
+        publish_event(&e, symbol_short!("updated"), subscription.owner, UpdatedEvent { subscription_id });
+    }
+
+    // Replaces the threshold and heartbeat of the subscription, the threshold applies to both directions.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `threshold` - New threshold in percentage
+    // * `heartbeat` - New heartbeat in minutes
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription has been cancelled or is suspended
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the heartbeat is less than the minimum
+    // Panics if the threshold is out of range
+    pub fn update_trigger_params(e: Env, subscription_id: u64, threshold: u32, heartbeat: u32) {
+        panic_if_not_initialized(&e);
+        let mut subscription = e.get_subscription(subscription_id).unwrap_or_else(|| {
+            if is_cancelled(&e, subscription_id) {
+                panic_with_error!(e, Error::InvalidSubscriptionStatusError);
+            }
+            panic_with_error!(e, Error::SubscriptionNotFound)
+        });
+        subscription.owner.require_auth();
+        if subscription.status == SubscriptionStatus::Suspended {
+            e.panic_with_error(Error::InvalidSubscriptionStatusError);
+        }
+        if !is_valid_heartbeat(&e, heartbeat) {
+            e.panic_with_error(Error::InvalidHeartbeat);
+        }
+        if !is_valid_threshold(threshold) {
+            e.panic_with_error(Error::InvalidThreshold);
+        }
+        subscription.threshold = threshold;
+        subscription.threshold_up = threshold;
+        subscription.threshold_down = threshold;
+        subscription.heartbeat = heartbeat;
+        e.set_subscription(subscription_id, &subscription);
+        publish_event(&e, symbol_short!("updated"), subscription.owner, UpdatedEvent { subscription_id });
+    }
+
+    // Withdraws funds from the subscription and deactivates it. Within the grace period a part of the activation fee is refunded as well.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // # Panics if the contract is not initialized
+    // # Panics if the subscription has already been cancelled
+    // # Panics if the subscription does not exist
+    // # Panics if the caller doesn't match the owner address
+    // # Panics if the subscription is hibernated
+    // # Panics if the token transfer fails
+    pub fn cancel(e: Env, subscription_id: u64) {
+        cancel_subscription(&e, subscription_id, None);
+    }
+
+    // Cancels the subscription like `cancel`, paying the remaining balance and the activation refund out to another address.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `to` - Payout address
+    //
+    // # Panics
+    //
+    // Panics in the same cases as `cancel`
+    pub fn cancel_to(e: Env, subscription_id: u64, to: Address) {
+        cancel_subscription(&e, subscription_id, Some(&to));
+    }
+
+    // Transfers the subscription to another owner, keeping its balance and status. Both the current and the new owner must authorize the transfer.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `new_owner` - New owner address
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription has been cancelled
+    // Panics if the subscription does not exist
+    // Panics if the new owner is the current owner
+    // Panics if the caller doesn't match the owner address
+    // Panics if the new owner doesn't authorize the transfer
+    // Panics if the new owner is not allowed by the registry
+    // Panics if the new owner holds the maximum number of subscriptions
+    pub fn transfer_ownership(e: Env, subscription_id: u64, new_owner: Address) {
+        panic_if_not_initialized(&e);
+        let mut subscription = e.get_subscription(subscription_id).unwrap_or_else(|| {
+            if is_cancelled(&e, subscription_id) {
+                panic_with_error!(e, Error::InvalidSubscriptionStatusError);
+            }
+            panic_with_error!(e, Error::SubscriptionNotFound)
+        });
+        if let Err(error) = validate_ownership_transfer(&subscription, &new_owner) {
+            e.panic_with_error(error);
+        }
+        subscription.owner.require_auth();
+        new_owner.require_auth();
+        if !is_owner_allowed(&e, &new_owner) {
+            e.panic_with_error(Error::NotAllowed);
+        }
+        if exceeds_owner_limit(&e, &new_owner) {
+            e.panic_with_error(Error::OwnerLimitExceeded);
+        }
+        let previous_owner = subscription.owner.clone();
+
+        let mut owner_subscriptions = e.get_owner_subscriptions(&previous_owner);
+        if let Some(index) = owner_subscriptions.first_index_of(subscription_id) {
+            owner_subscriptions.remove(index);
+            e.set_owner_subscriptions(&previous_owner, &owner_subscriptions);
+        }
+        // keep the index ordered by ID
+        let mut owner_subscriptions = e.get_owner_subscriptions(&new_owner);
+        let position = owner_subscriptions
+            .iter()
+            .position(|id| id > subscription_id)
+            .unwrap_or(owner_subscriptions.len() as usize);
+        owner_subscriptions.insert(position as u32, subscription_id);
+        e.set_owner_subscriptions(&new_owner, &owner_subscriptions);
+        if subscription.live_until > e.ledger().sequence() {
+            e.extend_owner_subscriptions_ttl(&new_owner, subscription.live_until - e.ledger().sequence());
+        }
+
+        subscription.owner = new_owner.clone();
+        e.set_subscription(subscription_id, &subscription);
+        let event = OwnerChangedEvent {
+            subscription_id,
+            previous_owner: previous_owner.clone(),
+            owner: new_owner,
+        };
+        publish_event(&e, symbol_short!("owner"), previous_owner, event);
+    }
+
+    // Merges the subscription into another subscription of the same owner and pair. The balance is moved, the merged subscription is removed without a refund.
+    //
+    // # Arguments
+    //
+    // * `keep_id` - ID of the subscription receiving the balance
+    // * `merge_id` - ID of the subscription to remove
+    //
+    // # Returns
+    //
+    // Updated balance of the kept subscription
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if either subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the subscriptions have different owners
+    // Panics if either subscription is not active
+    // Panics if the IDs are equal or the subscriptions are for different pairs or tokens
+    pub fn merge_subscriptions(e: Env, keep_id: u64, merge_id: u64) -> u64 {
+        panic_if_not_initialized(&e);
+        let mut subscription = e
+            .get_subscription(keep_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        let merged = e
+            .get_subscription(merge_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.owner.require_auth();
+        if merged.owner != subscription.owner {
+            e.panic_with_error(Error::NotOwner);
+        }
+        if subscription.status != SubscriptionStatus::Active || merged.status != SubscriptionStatus::Active {
+            e.panic_with_error(Error::InvalidSubscriptionStatusError);
+        }
+        if keep_id == merge_id
+            || merged.base != subscription.base
+            || merged.quote != subscription.quote
+            || merged.token != subscription.token
+        {
+            e.panic_with_error(Error::InvalidMerge);
+        }
+        // Both subscriptions are active, so the balance stays within the same totals
+        remove_subscription(&e, merge_id, &merged);
+        subscription.balance += merged.balance;
+        subscription.warned = false;
+        let subscription_fee = calc_fee(&e, &subscription.webhook);
+        let ledgers_to_live = calc_ledgers_to_live(&e, &subscription_fee, &subscription.balance);
+        track_ttl(&e, &mut subscription, ledgers_to_live);
+        e.set_subscription(keep_id, &subscription);
+        e.extend_subscription_ttl(keep_id, ledgers_to_live);
+        let event = MergedEvent {
+            subscription_id: keep_id,
+            merged_id: merge_id,
+            amount: merged.balance,
+        };
+        publish_event(&e, symbol_short!("merged"), subscription.owner, event);
+        subscription.balance
+    }
+
+    // Extends the TTL of the owner's subscriptions according to their balances.
+    //
+    // # Arguments
+    //
+    // * `owner` - Owner address
+    //
+    // # Returns
+    //
+    // Number of subscriptions bumped
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the caller doesn't match the owner address
+    pub fn bump_owner_ttl(e: Env, owner: Address) -> u32 {
+        panic_if_not_initialized(&e);
+        owner.require_auth();
+        let mut bumped = 0;
+        let mut max_ledgers_to_live = 0;
+        for subscription_id in e.get_owner_subscriptions(&owner).iter() {
+            if bumped >= MAX_TTL_BUMP_SIZE {
+                break;
+            }
+            if let Some(mut subscription) = e.get_subscription(subscription_id) {
+                let subscription_fee = calc_fee(&e, &subscription.webhook);
+                let ledgers_to_live = calc_ledgers_to_live(&e, &subscription_fee, &subscription.balance);
+                if track_ttl(&e, &mut subscription, ledgers_to_live) {
+                    e.set_subscription(subscription_id, &subscription);
+                }
+                e.extend_subscription_ttl(subscription_id, ledgers_to_live);
+                max_ledgers_to_live = max_ledgers_to_live.max(ledgers_to_live);
+                bumped += 1;
+            }
+        }
+        if bumped > 0 {
+            e.extend_owner_subscriptions_ttl(&owner, max_ledgers_to_live);
+        }
+        bumped
+    }
+
+    // Withdraws part of the subscription balance to the owner, keeping at least one fee on the balance.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    // * `amount` - Amount to withdraw
+    //
+    // # Returns
+    //
+    // Remaining balance
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription does not exist
+    // Panics if the caller doesn't match the owner address
+    // Panics if the subscription is suspended
+    // Panics if the amount is zero or the remaining balance would drop below the subscription fee
+    // Panics if the token transfer fails
+    pub fn withdraw(e: Env, subscription_id: u64, amount: u64) -> u64 {
+        panic_if_not_initialized(&e);
+        let mut subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.owner.require_auth();
+        // Suspended balances are refunded through the dust withdrawal
+        if subscription.status == SubscriptionStatus::Suspended {
+            e.panic_with_error(Error::InvalidSubscriptionStatusError);
+        }
+        if amount == 0 || exceeds_withdrawable(&e, &subscription, amount) {
+            e.panic_with_error(Error::InvalidAmount);
+        }
+        subscription.balance -= amount;
+        e.set_subscription(subscription_id, &subscription);
+        untrack_balance(&e, &subscription.token, subscription.status, amount);
+        decrease_tvl(&e, &subscription.token, amount);
+        transfer_tokens(&e, &subscription.token, &e.current_contract_address(), &subscription.owner, amount);
+        publish_event(&e, symbol_short!("withdrawn"), subscription.owner, WithdrawnEvent { subscription_id, amount });
+        subscription.balance
+    }
+
+    // Refunds the remaining balances of the owner's suspended subscriptions, keeping them suspended for later reactivation.
+    //
+    // # Arguments
+    //
+    // * `owner` - Owner address
+    //
+    // # Returns
+    //
+    // Total refunded amount
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the caller doesn't match the owner address
+    // Panics if the token transfer fails
+    pub fn withdraw_all_dust(e: Env, owner: Address) -> u64 {
+        panic_if_not_initialized(&e);
+        owner.require_auth();
+        let mut refunded = 0;
+        let mut total = 0;
+        // Subscriptions paid in the same token are refunded with a single transfer
+        let mut token_totals: Map<Address, u64> = Map::new(&e);
+        for subscription_id in e.get_owner_subscriptions(&owner).iter() {
+            if refunded >= MAX_WITHDRAW_SIZE {
+                break;
+            }
+            let Some(mut subscription) = e.get_subscription(subscription_id) else {
+                continue;
+            };
+            if subscription.status != SubscriptionStatus::Suspended || subscription.balance == 0 {
+                continue;
+            }
+            let amount = subscription.balance;
+            subscription.balance = 0;
+            e.set_subscription(subscription_id, &subscription);
+            untrack_balance(&e, &subscription.token, SubscriptionStatus::Suspended, amount);
+            publish_event(&e, symbol_short!("withdrawn"), owner.clone(), WithdrawnEvent { subscription_id, amount });
+            token_totals.set(subscription.token.clone(), token_totals.get(subscription.token).unwrap_or(0) + amount);
+            total += amount;
+            refunded += 1;
+        }
+        for (token, token_total) in token_totals.iter() {
+            transfer_tokens(&e, &token, &e.current_contract_address(), &owner, token_total);
+            decrease_tvl(&e, &token, token_total);
+        }
+        total
+    }
+
+    // Gets the subscription by ID.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // Subscription data
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    pub fn get_subscription(e: Env, subscription_id: u64) -> Subscription {
+        panic_if_not_initialized(&e);
+        e.get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound))
+    }
+
+    // Gets the subscription by ID without panicking.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // Subscription data, or None if the contract is not initialized or the subscription does not exist
+    pub fn find_subscription(e: Env, subscription_id: u64) -> Option<Subscription> {
+        if !e.is_initialized() {
+            return None;
+        }
+        e.get_subscription(subscription_id)
+    }
+
+    // Gets the IDs of the owner's subscriptions.
+    //
+    // # Arguments
+    //
+    // * `owner` - Owner address
+    //
+    // # Returns
+    //
+    // Subscription IDs ordered by ID
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    pub fn get_owner_subscriptions(e: Env, owner: Address) -> Vec<u64> {
+        panic_if_not_initialized(&e);
+        e.get_owner_subscriptions(&owner)
+    }
+
+    // Checks whether the subscription exists without panicking.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // True if the subscription exists, false if it was never created or has been cancelled
+    pub fn has_subscription(e: Env, subscription_id: u64) -> bool {
+        e.is_initialized() && e.get_subscription(subscription_id).is_some()
+    }
+
+    // Gets a page of subscriptions ordered by ID, skipping cancelled ones.
+    //
+    // # Arguments
+    //
+    // * `start_id` - First subscription ID to inspect
+    // * `limit` - Maximum number of subscriptions to return, capped by the query limit
+    //
+    // # Returns
+    //
+    // Subscription ID and data pairs, the next page starts after the last returned ID
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    pub fn get_subscriptions(e: Env, start_id: u64, limit: u32) -> Vec<(u64, Subscription)> {
+        panic_if_not_initialized(&e);
+        let limit = limit.min(MAX_QUERY_SIZE);
+        let last_id = e.get_last_subscription_id();
+        let mut subscriptions = Vec::new(&e);
+        let mut subscription_id = start_id.max(1);
+        while subscription_id <= last_id && subscriptions.len() < limit {
+            if let Some(subscription) = e.get_subscription(subscription_id) {
+                subscriptions.push_back((subscription_id, subscription));
+            }
+            subscription_id += 1;
+        }
+        subscriptions
+    }
+
+    // Gets the statuses of multiple subscriptions.
+    //
+    // # Arguments
+    //
+    // * `ids` - Subscription IDs
+    //
+    // # Returns
+    //
+    // Subscription ID and status pairs, status is None if the subscription does not exist
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the number of IDs exceeds the batch limit
+    pub fn statuses(e: Env, ids: Vec<u64>) -> Vec<(u64, Option<SubscriptionStatus>)> {
+        panic_if_not_initialized(&e);
+        if ids.len() > MAX_QUERY_SIZE {
+            e.panic_with_error(Error::BatchTooLarge);
+        }
+        let mut statuses = Vec::new(&e);
+        for subscription_id in ids.iter() {
+            statuses.push_back((subscription_id, get_status(&e, subscription_id)));
+        }
+        statuses
+    }
+
+    // Gets multiple subscriptions by ID.
+    //
+    // # Arguments
+    //
+    // * `ids` - Subscription IDs
+    //
+    // # Returns
+    //
+    // Subscription ID and data pairs, data is None if the subscription does not exist
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the number of IDs exceeds the batch limit
+    pub fn get_subscriptions_by_ids(e: Env, ids: Vec<u64>) -> Vec<(u64, Option<Subscription>)> {
+        panic_if_not_initialized(&e);
+        if ids.len() > MAX_QUERY_SIZE {
+            e.panic_with_error(Error::BatchTooLarge);
+        }
+        let mut subscriptions = Vec::new(&e);
+        for subscription_id in ids.iter() {
+            subscriptions.push_back((subscription_id, e.get_subscription(subscription_id)));
+        }
+        subscriptions
+    }
+
+    // Checks whether the subscription webhook has not expired. Expired webhooks must not be delivered to until renewed by the owner.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // True if the webhook is valid
+    //
+    // # Panics
+    //
+    // Panics if the subscription does not exist
+    pub fn webhook_valid(e: Env, subscription_id: u64) -> bool {
+        let subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.webhook_expires_at.is_none_or(|expires_at| now(&e) < expires_at)
+    }
+
+    // Returns the remaining storage TTL of the subscription, as tracked by the contract's own TTL extensions.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // Remaining TTL in ledgers
+    //
+    // # Panics
+    //
+    // Panics if the subscription does not exist
+    pub fn subscription_ttl(e: Env, subscription_id: u64) -> u32 {
+        let subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        subscription.live_until.saturating_sub(e.ledger().sequence())
+    }
+
+    // Returns the effective per-interval fee of the subscription, including the webhook size surcharge.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // Effective fee
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription does not exist
+    pub fn effective_fee(e: Env, subscription_id: u64) -> u64 {
+        panic_if_not_initialized(&e);
+        let subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        calc_fee(&e, &subscription.webhook)
+    }
+
+    // Returns the number of billable intervals accrued since the subscription was last charged.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // Number of intervals due
+    //
+    // # Panics
+    //
+    // Panics if the subscription does not exist
+    pub fn intervals_due(e: Env, subscription_id: u64) -> u64 {
+        let subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        now(&e).saturating_sub(subscription.updated) / subscription.charge_interval_ms
+    }
+
+    // Returns the projected timestamp of the charge that will suspend the subscription at its current balance and fee.
+    //
+    // # Arguments
+    //
+    // * `subscription_id` - Subscription ID
+    //
+    // # Returns
+    //
+    // Suspension timestamp in milliseconds, or u64::MAX if the subscription is not billed
+    //
+    // # Panics
+    //
+    // Panics if the contract is not initialized
+    // Panics if the subscription does not exist
+    pub fn suspension_timestamp(e: Env, subscription_id: u64) -> u64 {
+        panic_if_not_initialized(&e);
+        let subscription = e
+            .get_subscription(subscription_id)
+            .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+        calc_suspension_timestamp(&e, &subscription)
+    }
+
+    // Gets the number of active subscriptions. Suspended and hibernated subscriptions are not counted.
+    //
+    // # Returns
+    // Active subscriptions count
+    pub fn active_count(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_active_count()
+    }
+
+    // Gets the last subscription ID.
+    //
+    // # Returns
+    // Last subscription ID
+    pub fn last_id(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_last_subscription_id()
+    }
+
+    // Estimates the cost of a new subscription at the current base fee. The webhook storage surcharge is not included.
+    //
+    // # Arguments
+    //
+    // * `amount` - Amount to deposit on creation
+    //
+    // # Returns
+    //
+    // Activation fee and number of whole days the remaining balance funds
+    pub fn estimate(e: Env, amount: u64) -> (u64, u64) {
+        panic_if_not_initialized(&e);
+        let fee = e.get_fee();
+        let activation_fee = calc_activation_fee(&e, fee);
+        (activation_fee, amount.saturating_sub(activation_fee) / fee)
+    }
+
+    // Returns the validation limits for new subscriptions.
+    //
+    // # Returns
+    //
+    // Maximum webhook size in bytes, minimum heartbeat in minutes, minimum and maximum threshold in percentage
+    pub fn limits(e: Env) -> (u32, u32, u32, u32) {
+        (e.get_max_webhook_size(), e.get_min_heartbeat(), MIN_THRESHOLD, MAX_THRESHOLD)
+    }
+
+    // Returns the ID the next created subscription will be assigned. It's only a prediction, a concurrent creation can take the ID first.
+    //
+    // # Returns
+    // Next subscription ID
+    pub fn next_subscription_id(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_last_subscription_id() + 1
+    }
+
+    // Returns admin address of the contract.
+    //
+    // # Returns
+    //
+    // Contract admin account address
+    pub fn admin(e: Env) -> Option<Address> {
+        e.get_admin()
+    }
+
+    // Checks whether the address is the admin of the contract.
+    //
+    // # Arguments
+    //
+    // * `address` - Address to check
+    //
+    // # Returns
+    //
+    // True if the address is the admin, false otherwise or if the contract is not initialized
+    pub fn is_admin(e: Env, address: Address) -> bool {
+        e.get_admin() == Some(address)
+    }
+
+    // Returns the proposed admin address awaiting acceptance.
+    //
+    // # Returns
+    //
+    // Proposed admin address, if any
+    pub fn pending_admin(e: Env) -> Option<Address> {
+        e.get_pending_admin()
+    }
+
+    // Returns current protocol version of the contract.
+    //
+    // # Returns
+    //
+    // Contract protocol version
+    pub fn version(_e: Env) -> u32 {
+        env!("CARGO_PKG_VERSION")
+            .split(".")
+            .next()
+            .unwrap()
+            .parse::<u32>()
+            .unwrap()
+    }
+
+    // Returns the base fee of the contract.
+    //
+    // # Returns
+    //
+    // Base fee
+    pub fn fee(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_fee()
+    }
+
+    // Returns the base fee of the contract without panicking. Named `find_` as the client reserves the `try_` prefix.
+    //
+    // # Returns
+    //
+    // Base fee, or None if the contract is not initialized
+    pub fn find_fee(e: Env) -> Option<u64> {
+        if !e.is_initialized() {
+            return None;
+        }
+        Some(e.get_fee())
+    }
+
+    // Returns the webhook storage surcharge per 1024 bytes.
+    //
+    // # Returns
+    //
+    // Webhook byte fee
+    pub fn webhook_byte_fee(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_webhook_byte_fee()
+    }
+
+    // Returns the bounds of the per-subscription charge interval.
+    //
+    // # Returns
+    //
+    // Minimum and maximum charge interval in milliseconds
+    pub fn charge_interval_bounds(e: Env) -> (u64, u64) {
+        panic_if_not_initialized(&e);
+        e.get_charge_interval_bounds()
+    }
+
+    // Returns the maximum number of subscriptions a single owner may hold.
+    //
+    // # Returns
+    //
+    // Maximum number of subscriptions, 0 if not limited beyond the owner index bound
+    pub fn max_per_owner(e: Env) -> u32 {
+        panic_if_not_initialized(&e);
+        e.get_max_per_owner()
+    }
+
+    // Returns the instance storage TTL settings.
+    //
+    // # Returns
+    //
+    // TTL threshold and extension in ledgers
+    pub fn instance_ttl(e: Env) -> (u32, u32) {
+        panic_if_not_initialized(&e);
+        e.get_instance_ttl()
+    }
+
+    // Returns whether the debug events are emitted.
+    //
+    // # Returns
+    //
+    // True if the debug events are enabled
+    pub fn debug_events(e: Env) -> bool {
+        panic_if_not_initialized(&e);
+        e.get_debug_events()
+    }
+
+    // Returns whether the minimal events are emitted.
+    //
+    // # Returns
+    //
+    // True if the minimal events are enabled
+    pub fn minimal_events(e: Env) -> bool {
+        panic_if_not_initialized(&e);
+        e.get_minimal_events()
+    }
+
+    // Returns whether charging of subscriptions is paused.
+    //
+    // # Returns
+    //
+    // True if billing is paused
+    pub fn is_billing_paused(e: Env) -> bool {
+        panic_if_not_initialized(&e);
+        e.is_billing_paused()
+    }
+
+    // Returns whether the contract is paused.
+    //
+    // # Returns
+    //
+    // True if creation, funding and charging are paused
+    pub fn is_paused(e: Env) -> bool {
+        panic_if_not_initialized(&e);
+        e.is_paused()
+    }
+
+    // Returns whether partially elapsed intervals are charged proportionally.
+    //
+    // # Returns
+    //
+    // True if the charge is prorated
+    pub fn prorate_charge(e: Env) -> bool {
+        panic_if_not_initialized(&e);
+        e.get_prorate_charge()
+    }
+
+    // Returns the time an owner has to wait before recreating a cancelled subscription for the same pair.
+    //
+    // # Returns
+    //
+    // Recreate cooldown in milliseconds
+    pub fn recreate_cooldown(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_recreate_cooldown()
+    }
+
+    // Returns the minimum time between deposits to the same subscription.
+    //
+    // # Returns
+    //
+    // Minimum deposit interval in milliseconds
+    pub fn min_deposit_interval(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_min_deposit_interval()
+    }
+
+    // Returns the maximum priority a subscription can be created with.
+    //
+    // # Returns
+    //
+    // Maximum priority
+    pub fn max_priority(e: Env) -> u32 {
+        panic_if_not_initialized(&e);
+        e.get_max_priority()
+    }
+
+    // Returns what happens to the remaining balance of subscriptions suspended by the charge.
+    //
+    // # Returns
+    //
+    // Suspension behavior
+    pub fn suspend_behavior(e: Env) -> SuspendBehavior {
+        panic_if_not_initialized(&e);
+        e.get_suspend_behavior()
+    }
+
+    // Returns the reserve the contract keeps on top of the subscription balances when burning charged tokens.
+    //
+    // # Returns
+    //
+    // Minimum reserve
+    pub fn min_reserve(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_min_reserve()
+    }
+
+    // Returns the multiple of the subscription fee charged on activation of new and reactivated subscriptions.
+    //
+    // # Returns
+    //
+    // Activation fee factor
+    pub fn fee_factor(e: Env) -> u32 {
+        panic_if_not_initialized(&e);
+        e.get_fee_factor()
+    }
+
+    // Returns how long underfunded subscriptions stay active before the charge suspends them.
+    //
+    // # Returns
+    //
+    // Grace period in milliseconds, 0 if underfunded subscriptions are suspended immediately
+    pub fn suspend_grace(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_suspend_grace()
+    }
+
+    // Returns how many days before the projected suspension the owner is warned by the charge.
+    //
+    // # Returns
+    //
+    // Lead time in days, 0 if the warnings are disabled
+    pub fn pre_suspend_lead_days(e: Env) -> u32 {
+        panic_if_not_initialized(&e);
+        e.get_pre_suspend_lead_days()
+    }
+
+    // Returns the total balance custodied by the contract for subscriptions paid in the contract token.
+    //
+    // # Returns
+    //
+    // Total custodied balance
+    pub fn tvl(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        e.get_tvl(&e.get_token())
+    }
+
+    // Returns the total balances of active and suspended subscriptions paid in the contract token. Hibernated balances are not included.
+    //
+    // # Returns
+    //
+    // Active and suspended balance totals
+    pub fn balance_by_status(e: Env) -> (u64, u64) {
+        panic_if_not_initialized(&e);
+        (e.get_active_balance(), e.get_suspended_balance())
+    }
+
+    // Returns the sum of effective fees of all active subscriptions paid in the contract token, i.e. the amount burned per day.
+    // Subscriptions without a fee cap are projected at the current fees, their webhook surcharges are rounded once over all of them.
+    // Capped subscriptions are counted at the fee they were last tracked at, fee changes are picked up as they get charged.
+    //
+    // # Returns
+    //
+    // Projected daily revenue
+    pub fn projected_daily_revenue(e: Env) -> u64 {
+        panic_if_not_initialized(&e);
+        let uncapped_fees = e.get_uncapped_count() * e.get_fee() + e.get_uncapped_bytes() * e.get_webhook_byte_fee() / 1024;
+        uncapped_fees + e.get_capped_fees()
+    }
+
+    // Checks whether the contract token balance covers the total balance of all subscriptions paid in it.
+    //
+    // # Returns
+    //
+    // True if the contract is solvent, false signals an accounting error
+    pub fn verify_solvency(e: Env) -> bool {
+        panic_if_not_initialized(&e);
+        let token = e.get_token();
+        let balance = get_token_client(&e, &token).balance(&e.current_contract_address());
+        balance >= e.get_tvl(&token) as i128
+    }
+
+    // Returns the maximum total balance the contract will custody.
+    //
+    // # Returns
+    //
+    // Total balance cap, if set
+    pub fn max_tvl(e: Env) -> Option<u64> {
+        panic_if_not_initialized(&e);
+        e.get_max_tvl()
+    }
+
+    // Returns the registry contract gating subscription creation.
+    //
+    // # Returns
+    //
+    // Registry contract address, if set
+    pub fn registry(e: Env) -> Option<Address> {
+        panic_if_not_initialized(&e);
+        e.get_registry()
+    }
+
+    // Returns the account allowed to charge subscriptions besides the admin.
+    //
+    // # Returns
+    //
+    // Charger address, if set
+    pub fn charger(e: Env) -> Option<Address> {
+        panic_if_not_initialized(&e);
+        e.get_charger()
+    }
+
+    // Returns the last trigger.
+    //
+    // # Returns
+    //
+    // Timestamp and hash of the last trigger, if triggered
+    pub fn last_trigger(e: Env) -> Option<(u64, BytesN<32>)> {
+        panic_if_not_initialized(&e);
+        e.get_last_trigger()
+    }
+
+    // Returns the most recent triggers, up to the number kept in storage.
+    //
+    // # Arguments
+    //
+    // * `limit` - Maximum number of triggers to return
+    //
+    // # Returns
+    //
+    // Timestamp and hash pairs of the most recent triggers, oldest first
+    pub fn trigger_history(e: Env, limit: u32) -> Vec<(u64, BytesN<32>)> {
+        panic_if_not_initialized(&e);
+        let trigger_history = e.get_trigger_history();
+        let start = trigger_history.len().saturating_sub(limit);
+        trigger_history.slice(start..)
+    }
+
+    // Returns the default charge interval of subscriptions with the base asset.
+    //
+    // # Arguments
+    //
+    // * `asset` - Base asset
+    //
+    // # Returns
+    //
+    // Charge interval in milliseconds, if set
+    pub fn asset_interval(e: Env, asset: Asset) -> Option<u64> {
+        panic_if_not_initialized(&e);
+        e.get_asset_interval(&asset)
+    }
+
+    // Returns whether new subscriptions can be paid in the token.
+    //
+    // # Arguments
+    //
+    // * `token` - Token address
+    //
+    // # Returns
+    //
+    // True if the token is the contract token or allowed by the admin
+    pub fn is_token_allowed(e: Env, token: Address) -> bool {
+        panic_if_not_initialized(&e);
+        validate_payment_token(&e, &PaymentToken::Custom(token)).is_ok()
+    }
+
+    // Returns the maximum number of subscriptions that can reference the price source.
+    //
+    // # Arguments
+    //
+    // * `source` - Price source
+    //
+    // # Returns
+    //
+    // Maximum number of subscriptions, if capped
+    pub fn source_cap(e: Env, source: String) -> Option<u32> {
+        panic_if_not_initialized(&e);
+        e.get_source_cap(&source)
+    }
+
+    // Returns the display symbol of the asset.
+    //
+    // # Arguments
+    //
+    // * `asset` - Asset
+    //
+    // # Returns
+    //
+    // Display symbol, if set
+    pub fn asset_symbol(e: Env, asset: Asset) -> Option<Symbol> {
+        panic_if_not_initialized(&e);
+        e.get_asset_symbol(&asset)
+    }
+
+    // Returns the token address of the contract.
+    //
+    // # Returns
+    //
+    // Token address
+    pub fn token(e: Env) -> Address {
+        panic_if_not_initialized(&e);
+        e.get_token()
+    }
+
+    // Returns the token address of the contract without panicking.
+    //
+    // # Returns
+    //
+    // Token address, or None if the contract is not initialized
+    pub fn find_token(e: Env) -> Option<Address> {
+        if !e.is_initialized() {
+            return None;
+        }
+        Some(e.get_token())
+    }
+}
+
+fn panic_if_not_initialized(e: &Env) {
+    if !e.is_initialized() {
+        panic_with_error!(e, Error::NotInitialized);
+    }
+}
+
+fn get_status(e: &Env, subscription_id: u64) -> Option<SubscriptionStatus> {
+    e.get_subscription(subscription_id)
+        .map(|subscription| subscription.status)
+}
+
+// Validates and stores the new subscription funded by the funder, returns the subscription ID, data and activation fee
+fn create_subscription(e: &Env, new_subscription: SubscriptionInitParams, amount: u64, funder: &Address) -> (u64, Subscription, u64) {
+    let (subscription_id, subscription, init_fee) = open_subscription(e, new_subscription, amount);
+    // Transfer and burn the tokens
+    transfer_tokens_to_current_contract(e, &subscription.token, funder, amount);
+    burn_tokens(e, &subscription.token, init_fee - subscription.refund_hold);
+    (subscription_id, subscription, init_fee)
+}
+
+// Validates and stores the new subscription, returns the subscription ID, data and activation fee.
+// The tokens have to be transferred and burned by the caller.
+fn open_subscription(e: &Env, new_subscription: SubscriptionInitParams, amount: u64) -> (u64, Subscription, u64) {
+    panic_if_paused(e);
+    if !is_owner_allowed(e, &new_subscription.owner) {
+        e.panic_with_error(Error::NotAllowed);
+    }
+
+    let subscription_fee = calc_fee(e, &new_subscription.webhook);
+
+    // Check the amount
+    let init_fee = calc_activation_fee(e, subscription_fee);
+    if amount < init_fee {
+        e.panic_with_error(Error::InvalidAmount);
+    }
+
+    if !is_valid_heartbeat(e, new_subscription.heartbeat) {
+        e.panic_with_error(Error::InvalidHeartbeat);
+    }
+
+    if !is_valid_pair(&new_subscription.base, &new_subscription.quote) {
+        e.panic_with_error(Error::InvalidAssetPair);
+    }
+
+    // Legacy symmetric threshold applies to both directions unless overridden
+    let threshold_up = new_subscription.threshold_up.unwrap_or(new_subscription.threshold);
+    let threshold_down = new_subscription.threshold_down.unwrap_or(new_subscription.threshold);
+    if !is_valid_threshold(threshold_up) || !is_valid_threshold(threshold_down) {
+        e.panic_with_error(Error::InvalidThreshold);
+    }
+
+    if !is_valid_webhook_size(e, &new_subscription.webhook) {
+        e.panic_with_error(Error::WebhookTooLong);
+    }
+    if !is_valid_label(&new_subscription.label) {
+        e.panic_with_error(Error::LabelTooLong);
+    }
+
+    if !is_valid_webhook_expiration(e, new_subscription.webhook_expires_at) {
+        e.panic_with_error(Error::InvalidWebhookExpiration);
+    }
+
+    if new_subscription.priority > e.get_max_priority() {
+        e.panic_with_error(Error::InvalidPriority);
+    }
+
+    // An explicit interval takes precedence over the base asset default, falling back to one day
+    let charge_interval_ms = new_subscription
+        .charge_interval_ms
+        .or_else(|| e.get_asset_interval(&new_subscription.base.asset))
+        .unwrap_or(DAY);
+    let (min_charge_interval, max_charge_interval) = e.get_charge_interval_bounds();
+    if charge_interval_ms < min_charge_interval || charge_interval_ms > max_charge_interval {
+        e.panic_with_error(Error::InvalidChargeInterval);
+    }
+
+    if exceeds_owner_limit(e, &new_subscription.owner) {
+        e.panic_with_error(Error::OwnerLimitExceeded);
+    }
+
+    if is_recreate_cooling_down(e, &new_subscription.owner, &new_subscription.base, &new_subscription.quote) {
+        e.panic_with_error(Error::RecreateCooldown);
+    }
+
+    for source in subscription_sources(e, &new_subscription.base, &new_subscription.quote).iter() {
+        if exceeds_source_cap(e, &source) {
+            e.panic_with_error(Error::SourceCapExceeded);
+        }
+        e.set_source_count(&source, e.get_source_count(&source) + 1);
+    }
+
+    let token = validate_payment_token(e, &new_subscription.token).unwrap_or_else(|error| panic_with_error!(e, error));
+    // The refundable share of the activation fee stays in custody until the cancellation grace period ends
+    let refund_hold = calc_refund_hold(e, init_fee);
+    increase_tvl(e, &token, amount - init_fee + refund_hold);
+
+    //todo: check if the subscription is valid and the amount is enough
+    let subscription_id = e.get_last_subscription_id() + 1;
+    let now = now(e);
+    let mut subscription = Subscription {
+        owner: new_subscription.owner,
+        base: new_subscription.base,
+        quote: new_subscription.quote,
+        threshold: new_subscription.threshold,
+        threshold_up,
+        threshold_down,
+        heartbeat: new_subscription.heartbeat,
+        webhook: new_subscription.webhook,
+        balance: amount - init_fee,
+        status: SubscriptionStatus::Active,
+        updated: now,
+        last_deposit: now,
+        priority: new_subscription.priority,
+        live_until: 0,
+        format_version: SUBSCRIPTION_FORMAT_VERSION,
+        charge_interval_ms,
+        webhook_expires_at: new_subscription.webhook_expires_at,
+        last_deposit_seq: 0,
+        max_acceptable_fee: new_subscription.max_acceptable_fee,
+        warned: false,
+        token,
+        grace_until: None,
+        label: new_subscription.label,
+        tracked_fee: 0,
+        activation_fee: init_fee,
+        activated_at: now,
+        refund_hold,
+    };
+    let ledgers_to_live = calc_ledgers_to_live(e, &subscription_fee, &subscription.balance);
+    track_ttl(e, &mut subscription, ledgers_to_live);
+    track_active(e, &mut subscription);
+    e.set_subscription(subscription_id, &subscription);
+    e.set_last_subscription_id(subscription_id);
+    track_balance(e, &subscription.token, subscription.status, subscription.balance);
+
+    let mut owner_subscriptions = e.get_owner_subscriptions(&subscription.owner);
+    owner_subscriptions.push_back(subscription_id);
+    e.set_owner_subscriptions(&subscription.owner, &owner_subscriptions);
+
+    e.extend_subscription_ttl(subscription_id, ledgers_to_live);
+    e.extend_owner_subscriptions_ttl(&subscription.owner, ledgers_to_live);
+    let event = CreatedEvent {
+        subscription_id,
+        subscription: event_subscription(e, &subscription),
+        activation_fee: init_fee,
+        event_seq: next_event_seq(e),
+    };
+    publish_created(e, &subscription.owner, &subscription.base.asset, event);
+    (subscription_id, subscription, init_fee)
+}
+
+// Replaces the webhook on behalf of the owner, returns the subscription to store
+fn update_owner_webhook(e: &Env, subscription_id: u64, webhook: Bytes) -> Subscription {
+    panic_if_not_initialized(e);
+    let mut subscription = e.get_subscription(subscription_id).unwrap_or_else(|| {
+        if is_cancelled(e, subscription_id) {
+            panic_with_error!(e, Error::InvalidSubscriptionStatusError);
+        }
+        panic_with_error!(e, Error::SubscriptionNotFound)
+    });
+    subscription.owner.require_auth();
+    if !is_valid_webhook_size(e, &webhook) {
+        e.panic_with_error(Error::WebhookTooLong);
+    }
+    replace_webhook(e, &mut subscription, webhook);
+    subscription
+}
+
+// Keeps the projected revenue in line with the webhook surcharge
+fn replace_webhook(e: &Env, subscription: &mut Subscription, webhook: Bytes) {
+    let active = subscription.status == SubscriptionStatus::Active;
+    if active {
+        untrack_revenue(e, subscription);
+    }
+    subscription.webhook = webhook;
+    if active {
+        track_revenue(e, subscription);
+    }
+}
+
+// Refunds the balance and removes the subscription from storage and the owner index
+fn close_subscription(e: &Env, subscription_id: u64, subscription: &Subscription, refund_to: &Address) {
+    transfer_tokens(e, &subscription.token, &e.current_contract_address(), refund_to, subscription.balance);
+    decrease_tvl(e, &subscription.token, subscription.balance);
+    untrack_balance(e, &subscription.token, subscription.status, subscription.balance);
+    remove_subscription(e, subscription_id, subscription);
+}
+
+// Removes the subscription from storage, the counters and the owner index, leaving its balance to the caller
+fn remove_subscription(e: &Env, subscription_id: u64, subscription: &Subscription) {
+    e.remove_subscription(subscription_id);
+    // The activation fee share not refunded on cancellation is burned with the subscription
+    decrease_tvl(e, &subscription.token, subscription.refund_hold);
+    burn_tokens(e, &subscription.token, subscription.refund_hold);
+    for source in subscription_sources(e, &subscription.base, &subscription.quote).iter() {
+        e.set_source_count(&source, e.get_source_count(&source).saturating_sub(1));
+    }
+    if subscription.status == SubscriptionStatus::Active {
+        untrack_active(e, subscription);
+    }
+
+    let mut owner_subscriptions = e.get_owner_subscriptions(&subscription.owner);
+    if let Some(index) = owner_subscriptions.first_index_of(subscription_id) {
+        owner_subscriptions.remove(index);
+        e.set_owner_subscriptions(&subscription.owner, &owner_subscriptions);
+    }
+}
+
+fn deposit_to_subscription(e: &Env, from: &Address, subscription_id: u64, amount: u64, owner: Option<&Address>) -> (u64, u64) {
+    panic_if_not_initialized(e);
+    panic_if_paused(e);
+    from.require_auth();
+    if amount == 0 {
+        e.panic_with_error(Error::InvalidAmount);
+    }
+    let subscription = e
+        .get_subscription(subscription_id)
+        .unwrap_or_else(|| panic_with_error!(e, Error::SubscriptionNotFound));
+    if owner.is_some_and(|owner| subscription.owner != *owner) {
+        e.panic_with_error(Error::NotOwner);
+    }
+    if let Err(error) = validate_deposit(e, &subscription) {
+        e.panic_with_error(error);
+    }
+    fund_subscription(e, from, subscription_id, subscription, amount, 0)
+}
+
+// Cancels the subscription paying out to the address, or to the owner if not set
+fn cancel_subscription(e: &Env, subscription_id: u64, to: Option<&Address>) {
+    panic_if_not_initialized(e);
+    let mut subscription = e.get_subscription(subscription_id).unwrap_or_else(|| {
+        if is_cancelled(e, subscription_id) {
+            panic_with_error!(e, Error::AlreadyCancelled);
+        }
+        panic_with_error!(e, Error::SubscriptionNotFound)
+    });
+    subscription.owner.require_auth();
+    // Suspended subscriptions can be cancelled to reclaim the residual balance
+    match subscription.status {
+        SubscriptionStatus::Active | SubscriptionStatus::Suspended => {}
+        _ => {
+            e.panic_with_error(Error::InvalidSubscriptionStatusError);
+        }
+    }
+    let to = to.unwrap_or(&subscription.owner).clone();
+    // Refund the activation fee share from the custodied hold, the rest of it is burned on close
+    let activation_refund = calc_activation_refund(e, &subscription);
+    if activation_refund > 0 {
+        transfer_tokens(e, &subscription.token, &e.current_contract_address(), &to, activation_refund);
+        decrease_tvl(e, &subscription.token, activation_refund);
+        subscription.refund_hold -= activation_refund;
+    }
+    // Transfer the remaining balance to the payout address
+    close_subscription(e, subscription_id, &subscription, &to);
+
+    let recreate_cooldown = e.get_recreate_cooldown();
+    if recreate_cooldown > 0 {
+        // The record only has to outlive the cooldown
+        let ledgers_to_live = (recreate_cooldown.div_ceil(LEDGER_TIME) + 1).min(e.storage().max_ttl() as u64) as u32;
+        e.set_last_cancel(&subscription.owner, &subscription.base, &subscription.quote, now(e), ledgers_to_live);
+    }
+
+    let event = CancelledEvent {
+        subscription_id,
+        activation_refund,
+        event_seq: next_event_seq(e),
+    };
+    publish_event(e, symbol_short!("cancelled"), subscription.owner.clone(), event);
+}
+
+fn validate_create_batch(params: &Vec<SubscriptionInitParams>, amounts: &Vec<u64>) -> Result<(), Error> {
+    if params.len() != amounts.len() {
+        return Err(Error::BatchLengthMismatch);
+    }
+    if params.len() > MAX_CREATE_SIZE {
+        return Err(Error::BatchTooLarge);
+    }
+    Ok(())
+}
+
+// Applies the corrected balance without moving tokens, keeping the balance counters in sync
+fn correct_balance(e: &Env, subscription_id: u64, subscription: &mut Subscription, balance: u64) {
+    let previous_balance = subscription.balance;
+    if balance > previous_balance {
+        increase_tvl(e, &subscription.token, balance - previous_balance);
+    } else {
+        decrease_tvl(e, &subscription.token, previous_balance - balance);
+    }
+    untrack_balance(e, &subscription.token, subscription.status, previous_balance);
+    track_balance(e, &subscription.token, subscription.status, balance);
+    subscription.balance = balance;
+    e.set_subscription(subscription_id, subscription);
+    let event = AdjustedEvent {
+        subscription_id,
+        previous_balance,
+        balance,
+    };
+    publish_event(e, symbol_short!("adjusted"), subscription.owner.clone(), event);
+}
+
+// Debits are clamped at zero, None if the credit overflows the balance
+fn calc_adjusted_balance(balance: u64, delta: i128) -> Option<u64> {
+    let balance = (balance as i128).checked_add(delta)?;
+    u64::try_from(balance.max(0)).ok()
+}
+
+// Resolves the token the subscription is paid in, other tokens than the contract token have to be allowed by the admin
+fn validate_payment_token(e: &Env, token: &PaymentToken) -> Result<Address, Error> {
+    let contract_token = e.get_token();
+    match token {
+        PaymentToken::Default => Ok(contract_token),
+        PaymentToken::Custom(token) if *token == contract_token || e.is_token_allowed(token) => Ok(token.clone()),
+        PaymentToken::Custom(_) => Err(Error::TokenNotAllowed),
+    }
+}
+
+fn validate_instance_ttl(e: &Env, threshold: u32, extend_to: u32) -> Result<(), Error> {
+    if threshold > extend_to || extend_to > e.storage().max_ttl() {
+        return Err(Error::InvalidTtl);
+    }
+    Ok(())
+}
+
+// Both owners authorize the transfer, which can be required only once per address within the invocation
+fn validate_ownership_transfer(subscription: &Subscription, new_owner: &Address) -> Result<(), Error> {
+    if subscription.owner == *new_owner {
+        return Err(Error::SameOwner);
+    }
+    Ok(())
+}
+
+// The expected balance guards against corrections racing with charges and deposits
+fn validate_balance_correction(subscription: &Subscription, expected_old: u64) -> Result<(), Error> {
+    if subscription.balance != expected_old {
+        return Err(Error::BalanceMismatch);
+    }
+    Ok(())
+}
+
+fn validate_deposit(e: &Env, subscription: &Subscription) -> Result<(), Error> {
+    // Suspended subscriptions are funded through reactivation only
+    if subscription.status == SubscriptionStatus::Suspended {
+        return Err(Error::InvalidSubscriptionStatusError);
+    }
+    if is_deposit_too_soon(e, subscription) {
+        return Err(Error::DepositTooSoon);
+    }
+    Ok(())
+}
+
+// Activates the suspended subscription, returns the activation fee to burn from the amount
+fn resume_subscription(e: &Env, subscription: &mut Subscription, amount: u64) -> u64 {
+    // The reactivation is charged the same multiple of the subscription fee as the creation
+    let activation_fee = calc_activation_fee(e, calc_fee(e, &subscription.webhook));
+    if amount < activation_fee {
+        e.panic_with_error(Error::InvalidAmount);
+    }
+    subscription.status = SubscriptionStatus::Active;
+    track_active(e, subscription);
+    untrack_balance(e, &subscription.token, SubscriptionStatus::Suspended, subscription.balance);
+    track_balance(e, &subscription.token, SubscriptionStatus::Active, subscription.balance);
+    activation_fee
+}
+
+// Transfers the amount from the sender and credits it to the subscription, returns the credited and burned amounts
+fn fund_subscription(e: &Env, from: &Address, subscription_id: u64, subscription: Subscription, amount: u64, burn_amount: u64) -> (u64, u64) {
+    // Transfer and burn the tokens
+    transfer_tokens_to_current_contract(e, &subscription.token, from, amount);
+    burn_tokens(e, &subscription.token, burn_amount);
+    credit_subscription(e, from, subscription_id, subscription, amount, burn_amount)
+}
+
+// Credits the amount minus the burned part to the subscription, returns the credited and burned amounts.
+// The tokens have to be transferred and burned by the caller.
+fn credit_subscription(e: &Env, from: &Address, subscription_id: u64, mut subscription: Subscription, amount: u64, burn_amount: u64) -> (u64, u64) {
+    increase_tvl(e, &subscription.token, amount - burn_amount);
+
+    subscription.balance += amount - burn_amount;
+    track_balance(e, &subscription.token, subscription.status, amount - burn_amount);
+    subscription.last_deposit = now(e);
+    subscription.last_deposit_seq += 1;
+    subscription.warned = false;
+    let deposit_seq = subscription.last_deposit_seq;
+    let subscription_fee = calc_fee(e, &subscription.webhook);
+    let ledgers_to_live = calc_ledgers_to_live(e, &subscription_fee, &subscription.balance);
+    track_ttl(e, &mut subscription, ledgers_to_live);
+    e.set_subscription(subscription_id, &subscription);
+    e.extend_subscription_ttl(subscription_id, ledgers_to_live);
+    let event = DepositedEvent {
+        subscription_id,
+        subscription: event_subscription(e, &subscription),
+        amount,
+        deposit_seq,
+        from: event_account(e, from),
+        event_seq: next_event_seq(e),
+    };
+    publish_event(e, symbol_short!("deposited"), subscription.owner, event);
+    (amount - burn_amount, burn_amount)
+}
+
+// Advances the lifecycle event sequence, consumers detect missed events by gaps in it
+fn next_event_seq(e: &Env) -> u64 {
+    let event_seq = e.get_event_seq() + 1;
+    e.set_event_seq(event_seq);
+    event_seq
+}
+
+// Publishes the subscription event, omitting the owner from the topics if the minimal events are enabled
+fn publish_event<D: IntoVal<Env, Val>>(e: &Env, name: Symbol, owner: Address, data: D) {
+    if e.get_minimal_events() {
+        e.events().publish((REFLECTOR, name), data);
+    } else {
+        e.events().publish((REFLECTOR, name, owner), data);
+    }
+}
+
+// Returns the subscription data to publish, omitted if the minimal events are enabled
+fn event_subscription(e: &Env, subscription: &Subscription) -> EventSubscription {
+    if e.get_minimal_events() {
+        EventSubscription::Omitted
+    } else {
+        EventSubscription::Full(subscription.clone())
+    }
+}
+
+// The account is often the owner, so it's omitted along with the subscription data
+fn event_account(e: &Env, account: &Address) -> EventAccount {
+    if e.get_minimal_events() {
+        EventAccount::Omitted
+    } else {
+        EventAccount::Full(account.clone())
+    }
+}
+
+// The created event is additionally keyed by the base asset to stream the subscriptions of a pair, minimal events keep the common shape
+fn publish_created(e: &Env, owner: &Address, base: &Asset, event: CreatedEvent) {
+    if e.get_minimal_events() {
+        publish_event(e, symbol_short!("created"), owner.clone(), event);
+    } else {
+        e.events().publish((REFLECTOR, symbol_short!("created"), owner.clone(), asset_topic(e, base)), event);
+    }
+}
+
+// Event topics can't hold vectors, so the asset is represented by its address or symbol alone
+fn asset_topic(e: &Env, asset: &Asset) -> Val {
+    match asset {
+        Asset::Stellar(address) => address.into_val(e),
+        Asset::Other(symbol) => symbol.into_val(e),
+    }
+}
+
+fn publish_charge_skipped(e: &Env, subscription_id: u64, reason: Symbol) {
+    e.events().publish(
+        (REFLECTOR, Symbol::new(e, CHARGE_SKIPPED)),
+        ChargeSkippedEvent { subscription_id, reason },
+    );
+}
+
+fn panic_if_cannot_charge(e: &Env, caller: &Address) {
+    caller.require_auth();
+    if !is_charger(e, caller) {
+        e.panic_with_error(Error::Unauthorized);
+    }
+    if e.is_billing_paused() {
+        e.panic_with_error(Error::BillingPaused);
+    }
+    panic_if_paused(e);
+    // the periodic charge keeps the contract configuration alive between admin operations
+    e.extend_instance_ttl();
+}
+
+fn panic_if_paused(e: &Env) {
+    if let Err(error) = validate_not_paused(e) {
+        panic_with_error!(e, error);
+    }
+}
+
+fn validate_not_paused(e: &Env) -> Result<(), Error> {
+    if e.is_paused() {
+        return Err(Error::ContractPaused);
+    }
+    Ok(())
+}
+
+// Charges the subscriptions, returns the charged amount and the resulting status of each charged subscription
+fn charge_subscriptions(e: &Env, subscription_ids: &Vec<u64>) -> Vec<(u64, u64, SubscriptionStatus)> {
+    // Charges are accumulated per token to burn each token with a single call
+    let mut token_charges: Map<Address, u64> = Map::new(e);
+    let mut charged = Vec::new(e);
+    let now = now(e);
+    let debug_events = e.get_debug_events();
+    if debug_events {
+        for subscription_id in subscription_ids.iter() {
+            if e.get_subscription(subscription_id).is_none() {
+                publish_charge_skipped(e, subscription_id, symbol_short!("missing"));
+            }
+        }
+    }
+    for (subscription_id, mut subscription) in prioritize(e, subscription_ids, MAX_CHARGE_SIZE).iter() {
+        let (status, balance) = (subscription.status, subscription.balance);
+        let Some(charge) = apply_charge(e, &mut subscription, now) else {
+            if debug_events {
+                publish_charge_skipped(e, subscription_id, symbol_short!("not_due"));
+            }
+            continue;
+        };
+        if let Some(max_acceptable_fee) = subscription.max_acceptable_fee {
+            let fee = calc_fee(e, &subscription.webhook);
+            if fee > max_acceptable_fee {
+                let event = FeeCapHitEvent {
+                    subscription_id,
+                    fee,
+                    max_acceptable_fee,
+                };
+                publish_event(e, Symbol::new(e, FEE_CAP_HIT), subscription.owner.clone(), event);
+            }
+        }
+        warn_pre_suspend(e, subscription_id, &mut subscription, now);
+        if subscription.status == SubscriptionStatus::Suspended {
+            let event = SuspendedEvent {
+                timestamp: now,
+                subscription_id,
+                event_seq: next_event_seq(e),
+            };
+            publish_event(e, symbol_short!("suspended"), subscription.owner.clone(), event);
+            untrack_active(e, &subscription);
+            release_suspended_balance(e, &mut subscription);
+        } else {
+            // picks up the fee changes since the subscription was last tracked
+            refresh_tracked_fee(e, &mut subscription);
+        }
+        untrack_balance(e, &subscription.token, status, balance);
+        track_balance(e, &subscription.token, subscription.status, subscription.balance);
+        // The expired refund hold is burned along with the charge
+        let released = release_refund_hold(&mut subscription, now);
+        e.set_subscription(subscription_id, &subscription);
+
+        if charge + released > 0 {
+            let token_charge = token_charges.get(subscription.token.clone()).unwrap_or(0);
+            token_charges.set(subscription.token.clone(), token_charge + charge + released);
+        }
+        // Empty balances produce no charge to report
+        if charge > 0 {
+            let event = ChargedEvent {
+                timestamp: now,
+                subscription_id,
+                amount: charge,
+                event_seq: next_event_seq(e),
+            };
+            publish_event(e, symbol_short!("charged"), subscription.owner, event);
+        }
+
+        charged.push_back((subscription_id, charge, subscription.status));
+    }
+    for (token, total_charge) in token_charges.iter() {
+        decrease_tvl(e, &token, total_charge);
+
+        //Burn the tokens, keeping the reserve and the subscription balances in the contract
+        burn_tokens(e, &token, calc_burn_amount(e, &token, total_charge));
+    }
+    charged
+}
+
+fn prioritize(e: &Env, subscription_ids: &Vec<u64>, limit: u32) -> Vec<(u64, Subscription)> {
+    let mut subscriptions: Vec<(u64, Subscription)> = Vec::new(e);
+    for subscription_id in subscription_ids.iter() {
+        // Each copy would be charged from the same stored balance, so duplicates are processed once
+        if subscriptions.iter().any(|(id, _)| id == subscription_id) {
+            continue;
+        }
+        if let Some(subscription) = e.get_subscription(subscription_id) {
+            // Insert after all subscriptions with the same or higher priority to keep the batch order stable
+            let position = subscriptions
+                .iter()
+                .position(|(_, other)| other.priority < subscription.priority)
+                .unwrap_or(subscriptions.len() as usize);
+            subscriptions.insert(position as u32, (subscription_id, subscription));
+            if subscriptions.len() > limit {
+                subscriptions.pop_back();
+            }
+        }
+    }
+    subscriptions
+}
+
+// The subscription must keep at least one fee to avoid being suspended by the next charge
+fn exceeds_withdrawable(e: &Env, subscription: &Subscription, amount: u64) -> bool {
+    amount > subscription.balance.saturating_sub(calc_fee(e, &subscription.webhook))
+}
+
+// The configured limit can only be lower than the index bound, 0 leaves the index bound only
+fn exceeds_owner_limit(e: &Env, owner: &Address) -> bool {
+    let limit = match e.get_max_per_owner() {
+        0 => MAX_OWNER_SUBSCRIPTIONS,
+        limit => limit.min(MAX_OWNER_SUBSCRIPTIONS),
+    };
+    e.get_owner_subscriptions(owner).len() >= limit
+}
+
+// Issued IDs missing from storage belong to cancelled subscriptions
+fn is_cancelled(e: &Env, subscription_id: u64) -> bool {
+    subscription_id > 0
+        && subscription_id <= e.get_last_subscription_id()
+        && e.get_subscription(subscription_id).is_none()
+}
+
+fn is_charger(e: &Env, caller: &Address) -> bool {
+    e.get_admin().as_ref() == Some(caller) || e.get_charger().as_ref() == Some(caller)
+}
+
+fn is_owner_allowed(e: &Env, owner: &Address) -> bool {
+    let Some(registry) = e.get_registry() else {
+        return true;
+    };
+    // A failing or misbehaving registry is treated as disallowing the owner
+    let result = e.try_invoke_contract::<bool, soroban_sdk::Error>(
+        &registry,
+        &Symbol::new(e, IS_ALLOWED),
+        vec![e, owner.into_val(e)],
+    );
+    matches!(result, Ok(Ok(true)))
+}
+
+// The balances are totalled per token, the cap applies to each token
+fn increase_tvl(e: &Env, token: &Address, amount: u64) {
+    let tvl = e.get_tvl(token) + amount;
+    if exceeds_max_tvl(e, tvl) {
+        panic_with_error!(e, Error::TvlCapExceeded);
+    }
+    e.set_tvl(token, tvl);
+}
+
+fn decrease_tvl(e: &Env, token: &Address, amount: u64) {
+    // balances accrued before the counter was introduced are not tracked
+    e.set_tvl(token, e.get_tvl(token).saturating_sub(amount));
+}
+
+fn track_active(e: &Env, subscription: &mut Subscription) {
+    e.set_active_count(e.get_active_count() + 1);
+    track_revenue(e, subscription);
+}
+
+fn untrack_active(e: &Env, subscription: &Subscription) {
+    // subscriptions activated before the counters were introduced are not tracked
+    e.set_active_count(e.get_active_count().saturating_sub(1));
+    untrack_revenue(e, subscription);
+}
+
+// Uncapped subscriptions are counted to be projected at the current fees, capped ones at their current effective fee.
+// Fees paid in other tokens are not comparable with the contract token, so they are not counted in the revenue.
+fn track_revenue(e: &Env, subscription: &mut Subscription) {
+    subscription.tracked_fee = 0;
+    if subscription.token != e.get_token() {
+        return;
+    }
+    if subscription.max_acceptable_fee.is_some() {
+        subscription.tracked_fee = calc_effective_fee(e, subscription);
+        e.set_capped_fees(e.get_capped_fees() + subscription.tracked_fee);
+    } else {
+        e.set_uncapped_count(e.get_uncapped_count() + 1);
+        e.set_uncapped_bytes(e.get_uncapped_bytes() + subscription.webhook.len() as u64);
+    }
+}
+
+// The webhook and the fee cap have to be the tracked ones, so they are changed between the untracking and the tracking
+fn untrack_revenue(e: &Env, subscription: &Subscription) {
+    if subscription.token != e.get_token() {
+        return;
+    }
+    e.set_capped_fees(e.get_capped_fees().saturating_sub(subscription.tracked_fee));
+    if subscription.max_acceptable_fee.is_none() {
+        e.set_uncapped_count(e.get_uncapped_count().saturating_sub(1));
+        e.set_uncapped_bytes(e.get_uncapped_bytes().saturating_sub(subscription.webhook.len() as u64));
+    }
+}
+
+// Replaces the tracked fee of the active capped subscription with its current effective fee
+fn refresh_tracked_fee(e: &Env, subscription: &mut Subscription) {
+    untrack_revenue(e, subscription);
+    track_revenue(e, subscription);
+}
+
+// The daily fee the subscription is billed, including the webhook surcharge and the owner cap
+fn calc_effective_fee(e: &Env, subscription: &Subscription) -> u64 {
+    cap_fee(calc_fee(e, &subscription.webhook), subscription.max_acceptable_fee)
+}
+
+// Hibernated balances and balances in other tokens are tracked by the TVL only
+fn track_balance(e: &Env, token: &Address, status: SubscriptionStatus, amount: u64) {
+    if *token != e.get_token() {
+        return;
+    }
+    match status {
+        SubscriptionStatus::Active => e.set_active_balance(e.get_active_balance() + amount),
+        SubscriptionStatus::Suspended => e.set_suspended_balance(e.get_suspended_balance() + amount),
+        SubscriptionStatus::Hibernated => {}
+    }
+}
+
+fn untrack_balance(e: &Env, token: &Address, status: SubscriptionStatus, amount: u64) {
+    if *token != e.get_token() {
+        return;
+    }
+    // balances accrued before the counters were introduced are not tracked
+    match status {
+        SubscriptionStatus::Active => e.set_active_balance(e.get_active_balance().saturating_sub(amount)),
+        SubscriptionStatus::Suspended => e.set_suspended_balance(e.get_suspended_balance().saturating_sub(amount)),
+        SubscriptionStatus::Hibernated => {}
+    }
+}
+
+// A source shared by the base and the quote is referenced once
+fn subscription_sources(e: &Env, base: &TickerAsset, quote: &TickerAsset) -> Vec<String> {
+    let mut sources = vec![e, base.source.clone()];
+    if quote.source != base.source {
+        sources.push_back(quote.source.clone());
+    }
+    sources
+}
+
+// Checks whether one more subscription referencing the source would exceed its cap
+fn exceeds_source_cap(e: &Env, source: &String) -> bool {
+    matches!(e.get_source_cap(source), Some(cap) if e.get_source_count(source) >= cap)
+}
+
+fn exceeds_max_tvl(e: &Env, tvl: u64) -> bool {
+    matches!(e.get_max_tvl(), Some(max_tvl) if tvl > max_tvl)
+}
 
-fn transfer_tokens(e: &Env, from: &Address, to: &Address, amount: u64) {
-    let token_client = get_token_client(e);
+fn get_token_client<'a>(e: &'a Env, token: &Address) -> TokenClient<'a> {
+    TokenClient::new(e, token)
+}
+
+fn transfer_tokens_to_current_contract(e: &Env, token: &Address, from: &Address, amount: u64) {
+    transfer_tokens(e, token, from, &e.current_contract_address(), amount);
+}
+
+// Caps the burn so the contract balance never drops below the reserve plus the custodied subscription balances
+fn calc_burn_amount(e: &Env, token: &Address, amount: u64) -> u64 {
+    amount.min(calc_surplus(e, token))
+}
+
+// The contract balance exceeding the reserve plus the custodied subscription balances, the reserve is kept in the contract token only
+fn calc_surplus(e: &Env, token: &Address) -> u64 {
+    let balance = get_token_client(e, token).balance(&e.current_contract_address()) as u64;
+    let reserve = if *token == e.get_token() { e.get_min_reserve() } else { 0 };
+    balance.saturating_sub(reserve + e.get_tvl(token))
+}
+
+// The share of the activation fee held back from the burn at the current refund percentage
+fn calc_refund_hold(e: &Env, activation_fee: u64) -> u64 {
+    activation_fee * e.get_activation_refund_percent() as u64 / 100
+}
+
+// The refund is capped by the hold, so raising the percentage doesn't apply to the subscriptions created before
+fn calc_activation_refund(e: &Env, subscription: &Subscription) -> u64 {
+    if now(e).saturating_sub(subscription.activated_at) >= CANCEL_GRACE_PERIOD {
+        return 0;
+    }
+    calc_refund_hold(e, subscription.activation_fee).min(subscription.refund_hold)
+}
+
+// Releases the hold once the cancellation grace period is over, returns the amount to burn
+fn release_refund_hold(subscription: &mut Subscription, now: u64) -> u64 {
+    if now.saturating_sub(subscription.activated_at) < CANCEL_GRACE_PERIOD {
+        return 0;
+    }
+    let released = subscription.refund_hold;
+    subscription.refund_hold = 0;
+    released
+}
+
+// Burns the tokens held by the contract, batch operations accumulate the amount to burn it with a single call
+fn burn_tokens(e: &Env, token: &Address, amount: u64) {
+    if amount > 0 {
+        get_token_client(e, token).burn(&e.current_contract_address(), &(amount as i128));
+    }
+}
+
+fn transfer_tokens(e: &Env, token: &Address, from: &Address, to: &Address, amount: u64) {
+    let token_client = get_token_client(e, token);
     token_client.transfer(from, to, &(amount as i128));
 }
 
 fn now(e: &Env) -> u64 {
-    e.ledger().timestamp() * 1000 // normalize to milliseconds
+    to_millis(e.ledger().timestamp()).unwrap_or_else(|| panic_with_error!(e, Error::TimestampOverflow))
+}
+
+// Normalizes the timestamp to milliseconds, None on overflow
+fn to_millis(timestamp: u64) -> Option<u64> {
+    timestamp.checked_mul(1000)
+}
+
+// Charges the time elapsed since the billing anchor, prorating the partially elapsed interval, returns the charged amount
+fn settle_charge(e: &Env, subscription: &mut Subscription, now: u64) -> u64 {
+    if now <= subscription.updated {
+        return 0;
+    }
+    let charge = calc_charge(e, subscription, now - subscription.updated, true).min(subscription.balance);
+    subscription.balance -= charge;
+    subscription.updated = now;
+    charge
+}
+
+// Charges the elapsed intervals from the subscription balance, returns the charged amount or None if nothing is due
+fn apply_charge(e: &Env, subscription: &mut Subscription, now: u64) -> Option<u64> {
+    // Hibernated subscriptions are not billed
+    if subscription.status == SubscriptionStatus::Hibernated {
+        return None;
+    }
+    // Each charge advances the billing anchor, so the subscription can't be charged twice for the same time
+    if now <= subscription.updated {
+        return None;
+    }
+    let elapsed = now - subscription.updated;
+    let interval = subscription.charge_interval_ms;
+    let prorate = e.get_prorate_charge();
+    if elapsed < interval && !prorate {
+        return None;
+    }
+    let fee = scale_to_interval(cap_fee(calc_fee(e, &subscription.webhook), subscription.max_acceptable_fee), interval);
+    // Each elapsed interval is billed at the base fee in effect when it started
+    let mut charge = calc_charge(e, subscription, elapsed, prorate);
+    if subscription.balance < charge {
+        charge = subscription.balance;
+    }
+    subscription.balance -= charge;
+    // Without proration the partially elapsed interval is carried forward to the next charge
+    subscription.updated = if prorate { now } else { now - elapsed % interval };
+    if subscription.balance < fee {
+        // Deactivate the subscription if the balance is still less than the fee once the grace period is over
+        let grace_until = *subscription.grace_until.get_or_insert(now + e.get_suspend_grace());
+        if now >= grace_until {
+            subscription.status = SubscriptionStatus::Suspended;
+            subscription.grace_until = None;
+        }
+    } else {
+        subscription.grace_until = None;
+    }
+    Some(charge)
 }
 
-fn calc_fee(e: &Env, heartbeat: &u32, threshold: &u32) -> u64 {
-    //implement the fee calculation logic here
-    e.get_fee()
+// Applies the configured suspension behavior to the remaining balance of the suspended subscription
+fn release_suspended_balance(e: &Env, subscription: &mut Subscription) {
+    if subscription.balance == 0 {
+        return;
+    }
+    let recipient = match e.get_suspend_behavior() {
+        SuspendBehavior::Retain => return,
+        SuspendBehavior::DrainToTreasury(treasury) => treasury,
+        SuspendBehavior::Refund => subscription.owner.clone(),
+    };
+    transfer_tokens(e, &subscription.token, &e.current_contract_address(), &recipient, subscription.balance);
+    decrease_tvl(e, &subscription.token, subscription.balance);
+    subscription.balance = 0;
+}
+
+fn set_min_heartbeat(e: &Env, minutes: u32) {
+    if !is_valid_min_heartbeat(minutes) {
+        e.panic_with_error(Error::InvalidHeartbeat);
+    }
+    e.set_min_heartbeat(minutes);
+}
+
+fn set_fee(e: &Env, fee: u64) {
+    if !is_valid_fee(fee) {
+        e.panic_with_error(Error::InvalidFee);
+    }
+    e.set_fee(fee);
+    let mut fee_history = e.get_fee_history();
+    if fee_history.len() >= FEE_HISTORY_SIZE {
+        fee_history.pop_front();
+    }
+    fee_history.push_back((now(e), fee));
+    e.set_fee_history(&fee_history);
+}
+
+fn calc_fee(e: &Env, webhook: &Bytes) -> u64 {
+    // base fee plus the storage surcharge for the webhook size
+    e.get_fee() + calc_webhook_fee(e, webhook)
+}
+
+// The activation fee is a multiple of the subscription fee, twice by default
+fn calc_activation_fee(e: &Env, subscription_fee: u64) -> u64 {
+    subscription_fee * e.get_fee_factor() as u64
+}
+
+fn calc_webhook_fee(e: &Env, webhook: &Bytes) -> u64 {
+    webhook.len() as u64 * e.get_webhook_byte_fee() / 1024
+}
+
+fn calc_charge(e: &Env, subscription: &Subscription, elapsed: u64, prorate: bool) -> u64 {
+    let updated = subscription.updated;
+    let interval = subscription.charge_interval_ms;
+    let fee_history = e.get_fee_history();
+    let webhook_fee = calc_webhook_fee(e, &subscription.webhook);
+    // fall back to the oldest known fee for intervals preceding the history
+    let mut fee = fee_history.first().map(|(_, fee)| fee).unwrap_or_else(|| e.get_fee());
+    let mut index = 0;
+    let mut fee_at = |interval_start: u64| {
+        while let Some((timestamp, history_fee)) = fee_history.get(index) {
+            if timestamp > interval_start {
+                break;
+            }
+            fee = history_fee;
+            index += 1;
+        }
+        cap_fee(fee + webhook_fee, subscription.max_acceptable_fee)
+    };
+    let intervals = elapsed / interval;
+    let mut charge = 0;
+    for i in 0..intervals {
+        charge += scale_to_interval(fee_at(updated + i * interval), interval);
+    }
+    let remainder = elapsed % interval;
+    if prorate && remainder > 0 {
+        // bill the partially elapsed interval proportionally
+        charge += scale_to_interval(fee_at(updated + intervals * interval), remainder);
+    }
+    charge
+}
+
+fn cap_fee(fee: u64, max_acceptable_fee: Option<u64>) -> u64 {
+    max_acceptable_fee.map_or(fee, |max_fee| fee.min(max_fee))
+}
+
+fn calc_suspension_timestamp(e: &Env, subscription: &Subscription) -> u64 {
+    if subscription.status == SubscriptionStatus::Hibernated {
+        return u64::MAX;
+    }
+    let interval = subscription.charge_interval_ms;
+    let fee = scale_to_interval(cap_fee(calc_fee(e, &subscription.webhook), subscription.max_acceptable_fee), interval);
+    if fee == 0 {
+        return u64::MAX;
+    }
+    // the grace period is already running unless the balance has been topped up since
+    if let Some(grace_until) = subscription.grace_until.filter(|_| subscription.balance < fee) {
+        return grace_until;
+    }
+    // the charge leaving less than one interval fee suspends the subscription once the grace period is over
+    (subscription.balance / fee)
+        .saturating_mul(interval)
+        .saturating_add(subscription.updated)
+        .saturating_add(e.get_suspend_grace())
+}
+
+// Warns once per deposit about the suspension projected within the lead time
+fn warn_pre_suspend(e: &Env, subscription_id: u64, subscription: &mut Subscription, now: u64) {
+    let lead_days = e.get_pre_suspend_lead_days();
+    if lead_days == 0 || subscription.warned || subscription.status != SubscriptionStatus::Active {
+        return;
+    }
+    let suspends_at = calc_suspension_timestamp(e, subscription);
+    if suspends_at.saturating_sub(now) > lead_days as u64 * DAY {
+        return;
+    }
+    subscription.warned = true;
+    let event = PreSuspendWarningEvent { subscription_id, suspends_at };
+    publish_event(e, Symbol::new(e, PRE_SUSPEND_WARNING), subscription.owner.clone(), event);
+}
+
+// Scales the daily fee to the given duration in milliseconds
+fn scale_to_interval(daily_fee: u64, duration: u64) -> u64 {
+    (daily_fee as i128 * duration as i128 / DAY as i128) as u64
+}
+
+fn is_valid_webhook_expiration(e: &Env, webhook_expires_at: Option<u64>) -> bool {
+    webhook_expires_at.is_none_or(|expires_at| expires_at > now(e))
+}
+
+// A zero fee would provide the service for free and never suspend anyone
+fn is_valid_fee(fee: u64) -> bool {
+    fee > 0
+}
+
+// A zero minimum would let subscriptions trigger on every update
+fn is_valid_min_heartbeat(minutes: u32) -> bool {
+    minutes > 0
+}
+
+// The limit itself is inclusive
+fn is_valid_label(label: &Bytes) -> bool {
+    label.len() <= MAX_LABEL_SIZE
+}
+
+// The limit itself is inclusive
+fn is_valid_webhook_size(e: &Env, webhook: &Bytes) -> bool {
+    webhook.len() <= e.get_max_webhook_size()
+}
+
+fn is_valid_heartbeat(e: &Env, heartbeat: u32) -> bool {
+    heartbeat >= e.get_min_heartbeat()
+}
+
+fn is_valid_threshold(threshold: u32) -> bool {
+    (MIN_THRESHOLD..=MAX_THRESHOLD).contains(&threshold)
+}
+
+// The same asset from different sources makes a valid cross-source pair
+fn is_valid_pair(base: &TickerAsset, quote: &TickerAsset) -> bool {
+    base != quote
+}
+
+fn is_recreate_cooling_down(e: &Env, owner: &Address, base: &TickerAsset, quote: &TickerAsset) -> bool {
+    match e.get_last_cancel(owner, base, quote) {
+        Some(cancelled) => now(e) - cancelled < e.get_recreate_cooldown(),
+        None => false,
+    }
+}
+
+fn is_deposit_too_soon(e: &Env, subscription: &Subscription) -> bool {
+    now(e) - subscription.last_deposit < e.get_min_deposit_interval()
+}
+
+// Drops stale and duplicate entries from the owner index and makes sure it lists the subscription
+fn rebuild_owner_index(e: &Env, subscription_id: u64, subscription: &Subscription) {
+    let owner_subscriptions = e.get_owner_subscriptions(&subscription.owner);
+    let mut rebuilt = Vec::new(e);
+    for id in owner_subscriptions.iter() {
+        if id == subscription_id || rebuilt.contains(id) {
+            continue;
+        }
+        if e.get_subscription(id).is_some_and(|s| s.owner == subscription.owner) {
+            rebuilt.push_back(id);
+        }
+    }
+    // keep the index ordered by ID
+    let position = rebuilt.iter().position(|id| id > subscription_id).unwrap_or(rebuilt.len() as usize);
+    rebuilt.insert(position as u32, subscription_id);
+    if rebuilt != owner_subscriptions {
+        e.set_owner_subscriptions(&subscription.owner, &rebuilt);
+    }
+    if subscription.live_until > e.ledger().sequence() {
+        e.extend_owner_subscriptions_ttl(&subscription.owner, subscription.live_until - e.ledger().sequence());
+    }
+}
+
+// Records the ledger the subscription is kept alive until after extending its TTL, returns true if it changed
+fn track_ttl(e: &Env, subscription: &mut Subscription, extend_to: u32) -> bool {
+    // extensions never shorten the TTL and are clamped to the maximum
+    let live_until = e.ledger().sequence() + extend_to.min(e.storage().max_ttl());
+    if live_until <= subscription.live_until {
+        return false;
+    }
+    subscription.live_until = live_until;
+    true
 }
 
 fn calc_ledgers_to_live(e: &Env, fee: &u64, amount: &u64) -> u32 {
-    let days: u32 = ((amount + fee - 1) / fee) as u32;
+    let days: u32 = amount.div_ceil(*fee) as u32;
     let ledgers = days * 17280;
     if ledgers > e.storage().max_ttl() {
         panic_with_error!(e, Error::InvalidAmount);
@@ -2,13 +2,42 @@
 
 use super::*;
 use soroban_sdk::{
-    symbol_short, testutils::{storage::Persistent, Address as _, Ledger, LedgerInfo}, token::StellarAssetClient, vec, Bytes, Env, String
+    contracttype, symbol_short, testutils::{storage::{Instance, Persistent}, Address as _, Events, Ledger, LedgerInfo}, token::StellarAssetClient, vec, Bytes, Env, String, TryFromVal, Val
 };
 use types::{
     asset::Asset, contract_config::ContractConfig, subscription_init_params::SubscriptionInitParams,
     ticker_asset::TickerAsset,
 };
 
+// The subscription storage format preceding format versioning.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacySubscription {
+    pub owner: Address,
+    pub base: TickerAsset,
+    pub quote: TickerAsset,
+    pub threshold: u32,
+    pub heartbeat: u32,
+    pub webhook: Bytes,
+    pub balance: u64,
+    pub status: SubscriptionStatus,
+    pub updated: u64,
+}
+
+#[contract]
+pub struct MockRegistry;
+
+#[contractimpl]
+impl MockRegistry {
+    pub fn allow(e: Env, owner: Address) {
+        e.storage().instance().set(&owner, &true);
+    }
+
+    pub fn is_allowed(e: Env, owner: Address) -> bool {
+        e.storage().instance().has(&owner)
+    }
+}
+
 fn init_contract_with_admin<'a>() -> (Env, SubscriptionContractClient<'a>, ContractConfig) {
     let env = Env::default();
 
@@ -24,6 +53,7 @@ fn init_contract_with_admin<'a>() -> (Env, SubscriptionContractClient<'a>, Contr
         admin: admin.clone(),
         token,
         fee: 100,
+        min_heartbeat: None,
     };
 
     env.mock_all_auths();
@@ -34,6 +64,49 @@ fn init_contract_with_admin<'a>() -> (Env, SubscriptionContractClient<'a>, Contr
     (env, client, init_data)
 }
 
+fn subscription_params(env: &Env, owner: &Address, webhook_size: usize) -> SubscriptionInitParams {
+    let mut webhook = Bytes::new(env);
+    for _ in 0..webhook_size {
+        webhook.push_back(0);
+    }
+    SubscriptionInitParams {
+        owner: owner.clone(),
+        base: TickerAsset {
+            asset: Asset::Other(symbol_short!("BTC")),
+            source: String::from_str(env, "source1"),
+        },
+        quote: TickerAsset {
+            asset: Asset::Other(symbol_short!("ETH")),
+            source: String::from_str(env, "source2"),
+        },
+        threshold: 10,
+        threshold_up: None,
+        threshold_down: None,
+        heartbeat: 5,
+        webhook,
+        priority: 0,
+        charge_interval_ms: None,
+        webhook_expires_at: None,
+        max_acceptable_fee: None,
+        token: PaymentToken::Default,
+        label: Bytes::new(env),
+    }
+}
+
+fn funded_owner(env: &Env, config: &ContractConfig, amount: i128) -> Address {
+    let owner = Address::generate(env);
+    StellarAssetClient::new(env, &config.token).mint(&owner, &amount);
+    owner
+}
+
+fn set_timestamp(env: &Env, timestamp: u64) {
+    let ledger_info = env.ledger().get();
+    env.ledger().set(LedgerInfo {
+        timestamp,
+        ..ledger_info
+    });
+}
+
 #[test]
 fn test() {
     let (env, client, config) = init_contract_with_admin();
@@ -54,29 +127,45 @@ fn test() {
             source: String::from_str(&env, "source2"),
         },
         threshold: 10,
+        threshold_up: None,
+        threshold_down: None,
         heartbeat: 5,
         webhook: Bytes::from_array(&env, &[0; 2048]),
+        priority: 0,
+        charge_interval_ms: None,
+        webhook_expires_at: None,
+        max_acceptable_fee: None,
+        token: PaymentToken::Default,
+        label: Bytes::new(&env),
     };
 
     // create subscription
-    let (subscription_id, _) = client.create_subscription(&subscription, &200);
+    let (subscription_id, _, _) = client.create_subscription(&subscription, &200);
     assert!(subscription_id == 1);
 
+    // an empty balance doesn't extend the TTL beyond the initial one
+    let tracked_ttl = client.subscription_ttl(&subscription_id);
+    assert_eq!(tracked_ttl, 0);
     env.as_contract(&client.address, || {
         let ttl = env.storage().persistent().get_ttl(&subscription_id);
-        assert_eq!(ttl, ttl);
+        assert!(ttl >= tracked_ttl);
     });
 
     let trigger_hash: BytesN<32> = BytesN::from_array(&env, &[0; 32]);
     // heartbeat subscription
-    client.trigger(&1u64, &trigger_hash);
+    assert_eq!(client.last_trigger(), None);
+    let trigger = client.trigger(&1u64, &trigger_hash);
+    assert_eq!(trigger, (1u64, trigger_hash.clone()));
+    assert_eq!(client.last_trigger(), Some(trigger));
 
     // deposit subscription
     client.deposit(&owner, &1, &100);
 
+    let tracked_ttl = client.subscription_ttl(&subscription_id);
+    assert_eq!(tracked_ttl, 17280);
     env.as_contract(&client.address, || {
         let ttl = env.storage().persistent().get_ttl(&subscription_id);
-        assert_eq!(ttl, ttl);
+        assert_eq!(ttl, tracked_ttl);
     });
 
     let mut subs = client.get_subscription(&subscription_id);
@@ -89,7 +178,7 @@ fn test() {
     });
 
     // charge subscription
-    client.charge(&vec![&env, 1u64]);
+    client.charge(&config.admin, &vec![&env, 1u64]);
 
     // check balance and status
     subs = client.get_subscription(&subscription_id);
@@ -97,10 +186,10 @@ fn test() {
     assert_eq!(subs.status, SubscriptionStatus::Suspended);
     assert_eq!(subs.updated, 86400 * 2 * 1000);
 
-    // deposit subscription to renew
-    client.deposit(&owner, &1, &200);
+    // reactivate subscription
+    client.reactivate(&owner, &1, &300);
     subs = client.get_subscription(&subscription_id);
-    assert_eq!(subs.balance, 100); // 100 is activation fee
+    assert_eq!(subs.balance, 100); // 200 is activation fee
     assert_eq!(subs.status, SubscriptionStatus::Active);
 
     // cancel subscription
@@ -113,3 +202,2429 @@ fn test() {
     let last_id = client.last_id();
     assert_eq!(last_id, 1);
 }
+
+#[test]
+fn test_webhook_size_surcharge() {
+    let (env, client, config) = init_contract_with_admin();
+
+    let owner = funded_owner(&env, &config, 10_000);
+
+    // 50 per 1024 bytes on top of the base fee of 100
+    client.set_webhook_byte_fee(&50);
+    assert_eq!(client.webhook_byte_fee(), 50);
+
+    let (large_id, large, _) = client.create_subscription(&subscription_params(&env, &owner, 2048), &1000);
+    let (small_id, small, _) = client.create_subscription(&subscription_params(&env, &owner, 100), &1000);
+
+    assert_eq!(client.effective_fee(&large_id), 200);
+    assert_eq!(client.effective_fee(&small_id), 104);
+
+    // activation fee is 2 times the effective fee
+    assert_eq!(large.balance, 600);
+    assert_eq!(small.balance, 792);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, large_id, small_id]);
+
+    assert_eq!(client.get_subscription(&large_id).balance, 400);
+    assert_eq!(client.get_subscription(&small_id).balance, 688);
+}
+
+#[test]
+fn test_find_subscription() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, SubscriptionContract);
+    let uninitialized = SubscriptionContractClient::new(&env, &contract_id);
+    assert_eq!(uninitialized.find_subscription(&1), None);
+
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, subscription, _) =
+        client.create_subscription(&subscription_params(&env, &owner, 10), &1000);
+
+    assert_eq!(client.find_subscription(&subscription_id), Some(subscription));
+    assert_eq!(client.find_subscription(&2), None);
+}
+
+#[test]
+fn test_statuses() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (active_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let (suspended_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, suspended_id]);
+
+    let statuses = client.statuses(&vec![&env, active_id, suspended_id, 100]);
+    assert_eq!(
+        statuses,
+        vec![
+            &env,
+            (active_id, Some(SubscriptionStatus::Active)),
+            (suspended_id, Some(SubscriptionStatus::Suspended)),
+            (100, None)
+        ]
+    );
+}
+
+#[test]
+fn test_deposit_cooldown() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.min_deposit_interval(), 0);
+    client.set_min_deposit_interval(&(3600 * 1000));
+
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+
+    // rapid deposits are rejected
+    set_timestamp(&env, 60);
+    env.as_contract(&client.address, || {
+        let subscription = env.get_subscription(subscription_id).unwrap();
+        assert!(is_deposit_too_soon(&env, &subscription));
+    });
+
+    set_timestamp(&env, 3600);
+    client.deposit(&owner, &subscription_id, &10);
+    assert_eq!(client.get_subscription(&subscription_id).last_deposit, 3600 * 1000);
+
+    // suspend the subscription right after the deposit
+    set_timestamp(&env, 86400 + 3600);
+    client.deposit(&owner, &subscription_id, &1);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+
+    // reactivation bypasses the cooldown
+    client.reactivate(&owner, &subscription_id, &300);
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.status, SubscriptionStatus::Active);
+    assert_eq!(subscription.balance, 100);
+}
+
+#[test]
+fn test_bump_owner_ttl() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let other = funded_owner(&env, &config, 1000);
+    let (first_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &400);
+    let (second_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let (other_id, _, _) = client.create_subscription(&subscription_params(&env, &other, 10), &300);
+
+    let ledger_info = env.ledger().get();
+    env.ledger().set(LedgerInfo {
+        sequence_number: ledger_info.sequence_number + 1000,
+        ..ledger_info
+    });
+
+    assert_eq!(client.bump_owner_ttl(&owner), 2);
+    env.as_contract(&client.address, || {
+        let storage = env.storage().persistent();
+        // 2 days and 1 day of balance respectively
+        assert_eq!(storage.get_ttl(&first_id), 17280 * 2);
+        assert_eq!(storage.get_ttl(&second_id), 17280);
+        assert_eq!(storage.get_ttl(&other_id), 17280 - 1000);
+    });
+    assert_eq!(client.subscription_ttl(&first_id), 17280 * 2);
+    assert_eq!(client.subscription_ttl(&other_id), 17280 - 1000);
+
+    // cancelled subscriptions are dropped from the owner index
+    client.cancel(&first_id);
+    assert_eq!(client.bump_owner_ttl(&owner), 1);
+    assert_eq!(client.bump_owner_ttl(&Address::generate(&env)), 0);
+}
+
+#[test]
+fn test_asymmetric_threshold() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+
+    // legacy threshold maps to both directions
+    let (legacy_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let legacy = client.get_subscription(&legacy_id);
+    assert_eq!((legacy.threshold_up, legacy.threshold_down), (10, 10));
+
+    let mut params = subscription_params(&env, &owner, 10);
+    params.threshold_up = Some(500);
+    let (up_id, _, _) = client.create_subscription(&params, &300);
+    let up = client.get_subscription(&up_id);
+    assert_eq!((up.threshold_up, up.threshold_down), (500, 10));
+
+    params.threshold_down = Some(25);
+    let (both_id, _, _) = client.create_subscription(&params, &300);
+    let both = client.get_subscription(&both_id);
+    assert_eq!((both.threshold_up, both.threshold_down), (500, 25));
+}
+
+#[test]
+fn test_threshold_bounds() {
+    assert!(!is_valid_threshold(0));
+    assert!(is_valid_threshold(1));
+    assert!(is_valid_threshold(10000));
+    assert!(!is_valid_threshold(10001));
+}
+
+#[test]
+fn test_next_subscription_id() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.next_subscription_id(), 1);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    assert_eq!(subscription_id, 1);
+    assert_eq!(client.next_subscription_id(), 2);
+}
+
+#[test]
+fn test_limits() {
+    let (_, client, _) = init_contract_with_admin();
+    assert_eq!(client.limits(), (2048, 5, 1, 10000));
+}
+
+#[test]
+fn test_charge_with_fee_change() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &1000);
+
+    // the fee is raised in the middle of the second interval
+    set_timestamp(&env, 86400 + 43200);
+    client.set_fee(&200);
+
+    set_timestamp(&env, 86400 * 3);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+
+    // 100 for each of the first two intervals, 200 for the third one
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.balance, 800 - 400);
+    assert_eq!(subscription.updated, 86400 * 3 * 1000);
+}
+
+#[test]
+fn test_intervals_due() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &1000);
+    assert_eq!(client.intervals_due(&subscription_id), 0);
+
+    set_timestamp(&env, 86400 * 3 + 3600);
+    assert_eq!(client.intervals_due(&subscription_id), 3);
+
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.intervals_due(&subscription_id), 0);
+}
+
+#[test]
+fn test_registry() {
+    let (env, client, config) = init_contract_with_admin();
+    let allowed = funded_owner(&env, &config, 1000);
+    let denied = funded_owner(&env, &config, 1000);
+
+    let registry_id = env.register_contract(None, MockRegistry);
+    MockRegistryClient::new(&env, &registry_id).allow(&allowed);
+    client.set_registry(&Some(registry_id.clone()));
+    assert_eq!(client.registry(), Some(registry_id));
+
+    env.as_contract(&client.address, || {
+        assert!(is_owner_allowed(&env, &allowed));
+        assert!(!is_owner_allowed(&env, &denied));
+    });
+    client.create_subscription(&subscription_params(&env, &allowed, 10), &300);
+
+    // an address without the registry interface disallows everyone
+    client.set_registry(&Some(Address::generate(&env)));
+    env.as_contract(&client.address, || {
+        assert!(!is_owner_allowed(&env, &allowed));
+    });
+
+    client.set_registry(&None);
+    assert_eq!(client.registry(), None);
+    client.create_subscription(&subscription_params(&env, &denied, 10), &300);
+}
+
+#[test]
+fn test_charge_priority() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    client.set_max_priority(&2);
+    assert_eq!(client.max_priority(), 2);
+
+    let mut params = subscription_params(&env, &owner, 10);
+    let (low_id, _, _) = client.create_subscription(&params, &300);
+    params.priority = 2;
+    let (high_id, _, _) = client.create_subscription(&params, &300);
+    params.priority = 1;
+    let (medium_id, _, _) = client.create_subscription(&params, &300);
+    assert_eq!(client.get_subscription(&high_id).priority, 2);
+
+    // higher priority subscriptions are processed first under a truncated batch
+    env.as_contract(&client.address, || {
+        let batch = prioritize(&env, &vec![&env, low_id, high_id, 100, medium_id], 2);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.get_unchecked(0).0, high_id);
+        assert_eq!(batch.get_unchecked(1).0, medium_id);
+    });
+}
+
+#[test]
+fn test_tvl_cap() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.max_tvl(), None);
+    client.set_max_tvl(&Some(300));
+
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &400);
+    assert_eq!(client.tvl(), 200);
+
+    // deposits up to the cap boundary are accepted
+    client.deposit(&owner, &subscription_id, &100);
+    assert_eq!(client.tvl(), 300);
+    env.as_contract(&client.address, || {
+        assert!(!exceeds_max_tvl(&env, 300));
+        assert!(exceeds_max_tvl(&env, 301));
+    });
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.tvl(), 200);
+
+    client.cancel(&subscription_id);
+    assert_eq!(client.tvl(), 0);
+
+    client.set_max_tvl(&None);
+    env.as_contract(&client.address, || {
+        assert!(!exceeds_max_tvl(&env, u64::MAX));
+    });
+}
+
+#[test]
+fn test_charge_dry_run() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (funded_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let (underfunded_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+
+    set_timestamp(&env, 86400 * 2);
+    let ids = vec![&env, funded_id, underfunded_id, 100];
+    let prediction = client.charge_dry_run(&ids);
+    assert_eq!(
+        prediction,
+        vec![
+            &env,
+            (funded_id, 200, SubscriptionStatus::Active),
+            (underfunded_id, 50, SubscriptionStatus::Suspended)
+        ]
+    );
+    // nothing is written by the dry run
+    assert_eq!(client.get_subscription(&funded_id).balance, 300);
+    assert_eq!(client.tvl(), 350);
+
+    let balances = vec![
+        &env,
+        client.get_subscription(&funded_id).balance,
+        client.get_subscription(&underfunded_id).balance
+    ];
+    client.charge(&config.admin, &ids);
+    for (i, (subscription_id, charge, status)) in prediction.iter().enumerate() {
+        let subscription = client.get_subscription(&subscription_id);
+        assert_eq!(subscription.balance, balances.get_unchecked(i as u32) - charge);
+        assert_eq!(subscription.status, status);
+    }
+    assert_eq!(client.tvl(), 350 - 250);
+}
+
+#[test]
+fn test_legacy_subscription_upgrade() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let params = subscription_params(&env, &owner, 10);
+    let legacy = LegacySubscription {
+        owner: owner.clone(),
+        base: params.base.clone(),
+        quote: params.quote.clone(),
+        threshold: 15,
+        heartbeat: 5,
+        webhook: params.webhook.clone(),
+        balance: 300,
+        status: SubscriptionStatus::Active,
+        updated: 1000,
+    };
+    env.as_contract(&client.address, || {
+        env.storage().persistent().set(&1u64, &legacy);
+    });
+
+    let subscription = client.get_subscription(&1);
+    assert_eq!(subscription.format_version, SUBSCRIPTION_FORMAT_VERSION);
+    assert_eq!((subscription.threshold_up, subscription.threshold_down), (15, 15));
+    assert_eq!((subscription.balance, subscription.updated), (300, 1000));
+    assert_eq!((subscription.last_deposit, subscription.priority, subscription.live_until), (0, 0, 0));
+
+    // the upgraded entry is stored in the current format on the next write
+    client.deposit(&owner, &1, &100);
+    env.as_contract(&client.address, || {
+        let stored: Subscription = env.storage().persistent().get(&1u64).unwrap();
+        assert_eq!(stored.balance, 400);
+        assert_eq!(stored.format_version, SUBSCRIPTION_FORMAT_VERSION);
+    });
+}
+
+#[test]
+fn test_recreate_cooldown() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let params = subscription_params(&env, &owner, 10);
+    assert_eq!(client.recreate_cooldown(), 0);
+    client.set_recreate_cooldown(&(3600 * 1000));
+
+    let (subscription_id, _, _) = client.create_subscription(&params, &300);
+    client.cancel(&subscription_id);
+
+    // recreating the same pair right after cancelling is rejected
+    set_timestamp(&env, 60);
+    env.as_contract(&client.address, || {
+        assert!(is_recreate_cooling_down(&env, &owner, &params.base, &params.quote));
+        assert!(!is_recreate_cooling_down(&env, &Address::generate(&env), &params.base, &params.quote));
+        assert!(!is_recreate_cooling_down(&env, &owner, &params.quote, &params.base));
+    });
+
+    set_timestamp(&env, 3600);
+    env.as_contract(&client.address, || {
+        assert!(!is_recreate_cooling_down(&env, &owner, &params.base, &params.quote));
+    });
+    client.create_subscription(&params, &300);
+}
+
+#[test]
+fn test_charge_single_burn() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 10_000);
+    let mut ids = Vec::new(&env);
+    for _ in 0..5 {
+        let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+        ids.push_back(subscription_id);
+    }
+
+    let burn = symbol_short!("burn").into_val(&env);
+    let count_burns = || {
+        env.events()
+            .all()
+            .iter()
+            .filter(|(contract, topics, _)| *contract == config.token && topics.get(0).unwrap().shallow_eq(&burn))
+            .count()
+    };
+    let burns = count_burns();
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &ids);
+    assert_eq!(count_burns() - burns, 1);
+    assert_eq!(client.tvl(), 0);
+
+    // a batch of creations burns the activation fees once
+    let mut params = Vec::new(&env);
+    for _ in 0..3 {
+        params.push_back(subscription_params(&env, &owner, 10));
+    }
+    let burns = count_burns();
+    client.create_subscriptions(&params, &vec![&env, 300, 300, 300]);
+    assert_eq!(count_burns() - burns, 1);
+
+    // a batch of deposits resuming the suspended subscriptions burns the reactivation fees once
+    let mut deposits = Vec::new(&env);
+    for subscription_id in ids.iter() {
+        assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+        deposits.push_back((subscription_id, 300));
+    }
+    let burns = count_burns();
+    client.deposit_many(&owner, &deposits);
+    assert_eq!(count_burns() - burns, 1);
+    assert_eq!(client.tvl(), 3 * 100 + 5 * 100);
+}
+
+#[test]
+fn test_owner_deposit() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let sponsor = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    client.owner_deposit(&owner, &subscription_id, &100);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 200);
+
+    // sponsorship through the permissive deposit keeps working
+    client.deposit(&sponsor, &subscription_id, &50);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 250);
+}
+
+#[test]
+fn test_prorated_charge() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (integer_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let (prorated_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    assert!(!client.prorate_charge());
+
+    // integer charging skips the partial day
+    set_timestamp(&env, 86400 + 43200);
+    client.charge(&config.admin, &vec![&env, integer_id]);
+    assert_eq!(client.get_subscription(&integer_id).balance, 200);
+
+    // prorated charging bills the partial day proportionally
+    client.set_prorate_charge(&true);
+    client.charge(&config.admin, &vec![&env, prorated_id]);
+    let prorated = client.get_subscription(&prorated_id);
+    assert_eq!(prorated.balance, 150);
+    assert_eq!(prorated.updated, (86400 + 43200) * 1000);
+
+    // partial intervals are charged even before a whole one has elapsed
+    set_timestamp(&env, 86400 * 2);
+    client.charge(&config.admin, &vec![&env, prorated_id]);
+    assert_eq!(client.get_subscription(&prorated_id).balance, 100);
+}
+
+#[test]
+fn test_deposit_does_not_reactivate() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+
+    // deposits are rejected for suspended subscriptions
+    env.as_contract(&client.address, || {
+        let subscription = env.get_subscription(subscription_id).unwrap();
+        assert!(validate_deposit(&env, &subscription) == Err(Error::InvalidSubscriptionStatusError));
+    });
+
+    // the activation fee is burned, the rest is credited
+    assert_eq!(client.reactivate(&owner, &subscription_id, &250), (50, 200));
+    assert_eq!(client.get_subscription(&subscription_id).balance, 50);
+    env.as_contract(&client.address, || {
+        let subscription = env.get_subscription(subscription_id).unwrap();
+        assert!(validate_deposit(&env, &subscription).is_ok());
+    });
+    assert_eq!(client.deposit(&owner, &subscription_id, &50), (50, 0));
+}
+
+#[test]
+fn test_billing_pause() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    client.pause_billing();
+    assert!(client.is_billing_paused());
+
+    // deposits keep working while billing is paused
+    set_timestamp(&env, 86400);
+    client.deposit(&owner, &subscription_id, &100);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 200);
+
+    client.resume_billing();
+    assert!(!client.is_billing_paused());
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 100);
+}
+
+#[test]
+fn test_custom_charge_interval() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 10_000);
+    let day = 86400 * 1000;
+    client.set_charge_interval_bounds(&day, &(day * 7));
+    assert_eq!(client.charge_interval_bounds(), (day, day * 7));
+
+    let mut params = subscription_params(&env, &owner, 10);
+    let (daily_id, daily, _) = client.create_subscription(&params, &1200);
+    params.charge_interval_ms = Some(day * 7);
+    let (weekly_id, weekly, _) = client.create_subscription(&params, &1200);
+    assert_eq!(daily.charge_interval_ms, day);
+    assert_eq!(weekly.charge_interval_ms, day * 7);
+
+    // the weekly subscription is not due yet
+    set_timestamp(&env, 86400 * 3);
+    client.charge(&config.admin, &vec![&env, daily_id, weekly_id]);
+    assert_eq!(client.get_subscription(&daily_id).balance, 700);
+    assert_eq!(client.get_subscription(&weekly_id).balance, 1000);
+    assert_eq!(client.intervals_due(&weekly_id), 0);
+
+    // both are billed at the same daily rate
+    set_timestamp(&env, 86400 * 7);
+    assert_eq!(client.intervals_due(&weekly_id), 1);
+    client.charge(&config.admin, &vec![&env, daily_id, weekly_id]);
+    let daily = client.get_subscription(&daily_id);
+    let weekly = client.get_subscription(&weekly_id);
+    assert_eq!((daily.balance, daily.status), (300, SubscriptionStatus::Active));
+    // the remaining balance doesn't cover the next week
+    assert_eq!((weekly.balance, weekly.status), (300, SubscriptionStatus::Suspended));
+}
+
+#[test]
+fn test_charge_skipped_events() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    assert!(!client.debug_events());
+    client.set_debug_events(&true);
+
+    client.charge(&config.admin, &vec![&env, subscription_id, 100]);
+    let skipped: Vec<Val> = (REFLECTOR, Symbol::new(&env, "charge_skipped")).into_val(&env);
+    let mut skips: Vec<ChargeSkippedEvent> = Vec::new(&env);
+    for (contract, topics, data) in env.events().all().iter() {
+        if contract == client.address && topics == skipped {
+            skips.push_back(data.into_val(&env));
+        }
+    }
+    assert_eq!(
+        skips,
+        vec![
+            &env,
+            ChargeSkippedEvent { subscription_id: 100, reason: symbol_short!("missing") },
+            ChargeSkippedEvent { subscription_id, reason: symbol_short!("not_due") }
+        ]
+    );
+}
+
+#[test]
+fn test_min_reserve() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &1200);
+    assert_eq!(token.balance(&client.address), 1000);
+
+    client.set_min_reserve(&50);
+    assert_eq!(client.min_reserve(), 50);
+
+    // only the part of the charge above the reserve floor is burned
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 900);
+    assert_eq!(token.balance(&client.address), 950);
+
+    // the floor is already covered, the whole charge is burned
+    set_timestamp(&env, 86400 * 2);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(token.balance(&client.address), 850);
+}
+
+#[test]
+fn test_webhook_expiration() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let mut params = subscription_params(&env, &owner, 10);
+    params.webhook_expires_at = Some(3600 * 1000);
+    let (subscription_id, _, _) = client.create_subscription(&params, &300);
+    assert!(client.webhook_valid(&subscription_id));
+
+    set_timestamp(&env, 3599);
+    assert!(client.webhook_valid(&subscription_id));
+    set_timestamp(&env, 3600);
+    assert!(!client.webhook_valid(&subscription_id));
+    env.as_contract(&client.address, || {
+        assert!(!is_valid_webhook_expiration(&env, Some(3600 * 1000)));
+    });
+
+    // the owner renews the webhook
+    let webhook = Bytes::from_array(&env, &[1; 16]);
+    client.set_webhook(&subscription_id, &webhook, &Some(7200 * 1000));
+    assert!(client.webhook_valid(&subscription_id));
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.webhook, webhook);
+    assert_eq!(subscription.webhook_expires_at, Some(7200 * 1000));
+
+    client.set_webhook(&subscription_id, &webhook, &None);
+    set_timestamp(&env, 86400 * 365);
+    assert!(client.webhook_valid(&subscription_id));
+}
+
+#[test]
+fn test_suspend_behavior() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let treasury = Address::generate(&env);
+    let token = TokenClient::new(&env, &config.token);
+    assert_eq!(client.suspend_behavior(), SuspendBehavior::Retain);
+
+    // each subscription is left with 30 after the first charge
+    let params = subscription_params(&env, &owner, 10);
+    let (retained_id, _, _) = client.create_subscription(&params, &330);
+    let (drained_id, _, _) = client.create_subscription(&params, &330);
+    let (refunded_id, _, _) = client.create_subscription(&params, &330);
+    let owner_balance = token.balance(&owner);
+    set_timestamp(&env, 86400);
+
+    client.charge(&config.admin, &vec![&env, retained_id]);
+    let retained = client.get_subscription(&retained_id);
+    assert_eq!((retained.balance, retained.status), (30, SubscriptionStatus::Suspended));
+
+    client.set_suspend_behavior(&SuspendBehavior::DrainToTreasury(treasury.clone()));
+    client.charge(&config.admin, &vec![&env, drained_id]);
+    assert_eq!(client.get_subscription(&drained_id).balance, 0);
+    assert_eq!(token.balance(&treasury), 30);
+
+    client.set_suspend_behavior(&SuspendBehavior::Refund);
+    client.charge(&config.admin, &vec![&env, refunded_id]);
+    assert_eq!(client.get_subscription(&refunded_id).balance, 0);
+    assert_eq!(token.balance(&owner), owner_balance + 30);
+
+    assert_eq!(client.tvl(), 30);
+}
+
+#[test]
+fn test_create_subscription_activation_fee() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    client.set_webhook_byte_fee(&50);
+
+    let (_, subscription, activation_fee) =
+        client.create_subscription(&subscription_params(&env, &owner, 2048), &1000);
+    assert_eq!(activation_fee, 400);
+    // everything not burned is kept as the subscription balance
+    assert_eq!(token.balance(&client.address), 1000 - activation_fee as i128);
+    assert_eq!(subscription.balance, 1000 - activation_fee);
+}
+
+#[test]
+fn test_verify_solvency() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    assert!(client.verify_solvency());
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert!(client.verify_solvency());
+
+    // the counter claiming more than the contract holds is caught
+    env.as_contract(&client.address, || {
+        env.set_tvl(&config.token, env.get_tvl(&config.token) + 1);
+    });
+    assert!(!client.verify_solvency());
+}
+
+#[test]
+fn test_hibernate() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+
+    client.hibernate(&subscription_id);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Hibernated);
+
+    // charge skips hibernated subscriptions
+    set_timestamp(&env, 86400 * 3);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 300);
+
+    client.wake(&subscription_id);
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.status, SubscriptionStatus::Active);
+    assert_eq!(subscription.updated, 86400 * 3 * 1000);
+
+    // billing resumes from the wake time
+    set_timestamp(&env, 86400 * 4);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 200);
+}
+
+#[test]
+fn test_hibernate_before_due() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+
+    // hibernating and waking right before each charge is due still bills the elapsed time
+    for day in 1..=2 {
+        set_timestamp(&env, 86400 * day - 864);
+        client.hibernate(&subscription_id);
+        client.wake(&subscription_id);
+    }
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.balance, 300 - 99 - 100);
+    assert_eq!(subscription.updated, (86400 * 2 - 864) * 1000);
+    assert_eq!((client.tvl(), token.balance(&client.address)), (101, 101));
+    assert_eq!(client.balance_by_status(), (101, 0));
+
+    // the settled time is not billed again
+    set_timestamp(&env, 86400 * 3 - 864);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 1);
+}
+
+#[test]
+fn test_rebuild_indexes() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let other = funded_owner(&env, &config, 1000);
+    let (first_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &200);
+    let (second_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &200);
+    let (other_id, _, _) = client.create_subscription(&subscription_params(&env, &other, 10), &200);
+
+    // corrupt the indexes: lose an entry, add a foreign and a duplicate one, drop the other index entirely
+    env.as_contract(&client.address, || {
+        env.set_owner_subscriptions(&owner, &vec![&env, second_id, other_id, second_id, 42]);
+        env.set_owner_subscriptions(&other, &Vec::new(&env));
+    });
+
+    // resumable in batches
+    assert_eq!(client.rebuild_indexes(&0, &2), 3);
+    assert_eq!(client.rebuild_indexes(&3, &2), 0);
+    env.as_contract(&client.address, || {
+        assert_eq!(env.get_owner_subscriptions(&owner), vec![&env, first_id, second_id]);
+        assert_eq!(env.get_owner_subscriptions(&other), vec![&env, other_id]);
+    });
+
+    // idempotent
+    assert_eq!(client.rebuild_indexes(&1, &100), 0);
+    env.as_contract(&client.address, || {
+        assert_eq!(env.get_owner_subscriptions(&owner), vec![&env, first_id, second_id]);
+        assert_eq!(env.get_owner_subscriptions(&other), vec![&env, other_id]);
+    });
+}
+
+#[test]
+fn test_charger_role() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let charger = Address::generate(&env);
+
+    assert_eq!(client.charger(), None);
+    client.set_charger(&Some(charger.clone()));
+    assert_eq!(client.charger(), Some(charger.clone()));
+
+    set_timestamp(&env, 86400);
+    let balance = client.get_subscription(&subscription_id).balance;
+    client.charge(&charger, &vec![&env, subscription_id]);
+    assert!(client.get_subscription(&subscription_id).balance < balance);
+
+    // the admin keeps the right to charge, other accounts are rejected
+    env.as_contract(&client.address, || {
+        assert!(is_charger(&env, &config.admin));
+        assert!(is_charger(&env, &charger));
+        assert!(!is_charger(&env, &owner));
+    });
+
+    client.set_charger(&None);
+    env.as_contract(&client.address, || {
+        assert!(!is_charger(&env, &charger));
+        assert!(is_charger(&env, &config.admin));
+    });
+}
+
+#[test]
+fn test_projected_daily_revenue() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 10000);
+    // one token unit per webhook byte keeps the per-subscription surcharges unrounded
+    client.set_webhook_byte_fee(&1024);
+
+    let scan = || {
+        env.as_contract(&client.address, || {
+            let mut revenue = 0;
+            for subscription_id in 1..=env.get_last_subscription_id() {
+                if let Some(subscription) = env.get_subscription(subscription_id) {
+                    if subscription.status == SubscriptionStatus::Active {
+                        revenue += calc_effective_fee(&env, &subscription);
+                    }
+                }
+            }
+            revenue
+        })
+    };
+
+    let (suspended_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &240);
+    let (hibernated_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 20), &400);
+    let (cancelled_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 30), &600);
+    assert_eq!(client.projected_daily_revenue(), 100 * 3 + 60);
+    assert_eq!(client.projected_daily_revenue(), scan());
+
+    client.hibernate(&hibernated_id);
+    assert_eq!(client.projected_daily_revenue(), scan());
+
+    client.set_webhook(&cancelled_id, &Bytes::from_array(&env, &[0; 50]), &None);
+    assert_eq!(client.projected_daily_revenue(), scan());
+    client.set_max_acceptable_fee(&cancelled_id, &Some(140));
+    assert_eq!(client.projected_daily_revenue(), 110 + 140);
+    assert_eq!(client.projected_daily_revenue(), scan());
+
+    // base fee changes are projected right away
+    client.set_fee(&120);
+    assert_eq!(client.projected_daily_revenue(), 130 + 140);
+    assert_eq!(client.projected_daily_revenue(), scan());
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, suspended_id, cancelled_id]);
+    assert_eq!(client.get_subscription(&suspended_id).status, SubscriptionStatus::Suspended);
+    assert_eq!(client.get_subscription(&cancelled_id).status, SubscriptionStatus::Active);
+    assert_eq!(client.projected_daily_revenue(), scan());
+
+    client.reactivate(&owner, &suspended_id, &300);
+    client.wake(&hibernated_id);
+    assert_eq!(client.projected_daily_revenue(), scan());
+
+    client.cancel(&cancelled_id);
+    assert_eq!(client.projected_daily_revenue(), 130 + 140);
+    assert_eq!(client.projected_daily_revenue(), scan());
+
+    // byte fee changes are projected right away, the surcharges are rounded once over all uncapped subscriptions
+    client.set_webhook_byte_fee(&1);
+    assert_eq!(client.projected_daily_revenue(), 120 * 2);
+    assert_eq!(client.projected_daily_revenue(), scan());
+    for _ in 0..3 {
+        client.create_subscription(&subscription_params(&env, &owner, 400), &300);
+    }
+    assert_eq!(client.projected_daily_revenue(), 120 * 5 + (10 + 20 + 400 * 3) / 1024);
+}
+
+#[test]
+fn test_find_config_values() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, SubscriptionContract);
+    let uninitialized = SubscriptionContractClient::new(&env, &contract_id);
+    assert_eq!(uninitialized.find_fee(), None);
+    assert_eq!(uninitialized.find_token(), None);
+
+    let (_, client, config) = init_contract_with_admin();
+    assert_eq!(client.find_fee(), Some(config.fee));
+    assert_eq!(client.find_token(), Some(config.token));
+}
+
+#[test]
+fn test_deposit_sequence() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    assert_eq!(subscription.last_deposit_seq, 0);
+
+    for deposit_seq in 1..=3u64 {
+        client.deposit(&owner, &subscription_id, &10);
+        assert_eq!(client.get_subscription(&subscription_id).last_deposit_seq, deposit_seq);
+        // the sequence number is stamped on the deposit event
+        let (_, _, data) = env.events().all().last().unwrap();
+        let event = DepositedEvent::try_from_val(&env, &data).unwrap();
+        assert_eq!((event.amount, event.deposit_seq), (10, deposit_seq));
+        let EventSubscription::Full(subscription) = event.subscription else {
+            panic!("the subscription data is omitted");
+        };
+        assert_eq!(subscription.last_deposit_seq, deposit_seq);
+    }
+}
+
+#[test]
+fn test_asset_symbol() {
+    let (env, client, _) = init_contract_with_admin();
+    let usdc = Asset::Stellar(Address::generate(&env));
+    let other = Asset::Stellar(Address::generate(&env));
+    assert_eq!(client.asset_symbol(&usdc), None);
+
+    client.set_asset_symbol(&usdc, &symbol_short!("USDC"));
+    assert_eq!(client.asset_symbol(&usdc), Some(symbol_short!("USDC")));
+    assert_eq!(client.asset_symbol(&other), None);
+
+    client.set_asset_symbol(&usdc, &symbol_short!("USDC2"));
+    assert_eq!(client.asset_symbol(&usdc), Some(symbol_short!("USDC2")));
+}
+
+#[test]
+fn test_charge_window() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (early_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    set_timestamp(&env, 3600);
+    let (in_window_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    set_timestamp(&env, 7200);
+    let (late_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let balance = client.get_subscription(&in_window_id).balance;
+
+    // the subscriptions in the window that are not due yet are not selected
+    set_timestamp(&env, 86400 + 1800);
+    assert_eq!(client.charge_window(&config.admin, &0, &(7200 * 1000), &1, &10), 1);
+    assert!(client.get_subscription(&early_id).balance < balance);
+    assert_eq!(client.get_subscription(&in_window_id).balance, balance);
+
+    set_timestamp(&env, 86400 + 7200);
+    // only the IDs in the range are inspected
+    assert_eq!(client.charge_window(&config.admin, &(3600 * 1000), &(7200 * 1000), &late_id, &10), 0);
+    assert_eq!(client.charge_window(&config.admin, &(3600 * 1000), &(7200 * 1000), &1, &10), 1);
+    assert!(client.get_subscription(&in_window_id).balance < balance);
+    assert_eq!(client.get_subscription(&late_id).balance, balance);
+
+    // the charged subscription moves out of the window
+    assert_eq!(client.charge_window(&config.admin, &(3600 * 1000), &(7200 * 1000), &1, &10), 0);
+}
+
+#[test]
+fn test_max_acceptable_fee() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let mut params = subscription_params(&env, &owner, 10);
+    params.max_acceptable_fee = Some(120);
+    let (subscription_id, subscription, _) = client.create_subscription(&params, &500);
+    assert_eq!(subscription.max_acceptable_fee, Some(120));
+    client.set_max_acceptable_fee(&subscription_id, &Some(100));
+
+    // the fee hike above the cap is billed at the cap
+    client.set_fee(&150);
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 300 - 100);
+    let topics: Vec<Val> = (REFLECTOR, Symbol::new(&env, FEE_CAP_HIT), owner.clone()).into_val(&env);
+    let fee_cap_hit = env.events().all().iter().filter(|(_, event_topics, _)| *event_topics == topics).count();
+    assert_eq!(fee_cap_hit, 1);
+
+    // lifting the cap restores the full fee
+    client.set_max_acceptable_fee(&subscription_id, &None);
+    set_timestamp(&env, 86400 * 2);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 200 - 150);
+}
+
+#[test]
+fn test_create_sponsored() {
+    let (env, client, config) = init_contract_with_admin();
+    let treasury = funded_owner(&env, &config, 1000);
+    let owner = Address::generate(&env);
+    let token = TokenClient::new(&env, &config.token);
+
+    let (subscription_id, subscription, activation_fee) =
+        client.create_sponsored(&subscription_params(&env, &owner, 10), &300, &treasury);
+    assert_eq!(subscription.owner, owner);
+    assert_eq!(subscription.balance, 300 - activation_fee);
+    assert_eq!(token.balance(&treasury), 700);
+    assert_eq!(token.balance(&owner), 0);
+
+    // the owner controls the sponsored subscription
+    client.cancel(&subscription_id);
+    assert_eq!(token.balance(&owner), 300 - activation_fee as i128);
+}
+
+#[test]
+fn test_suspension_timestamp() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &450);
+    assert_eq!(subscription.balance, 250);
+
+    // 250 covers two daily fees, the second charge leaves less than a fee
+    let suspension_timestamp = client.suspension_timestamp(&subscription_id);
+    assert_eq!(suspension_timestamp, 2 * DAY);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Active);
+    assert_eq!(client.suspension_timestamp(&subscription_id), suspension_timestamp);
+
+    set_timestamp(&env, suspension_timestamp / 1000);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+
+    // subscriptions capped at a zero fee never suspend
+    client.set_max_acceptable_fee(&subscription_id, &Some(0));
+    assert_eq!(client.suspension_timestamp(&subscription_id), u64::MAX);
+}
+
+#[test]
+fn test_minimal_events() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert!(!client.minimal_events());
+    client.set_minimal_events(&true);
+    assert!(client.minimal_events());
+
+    let contract_events = || {
+        let mut events = Vec::new(&env);
+        for (contract, topics, data) in env.events().all().iter() {
+            if contract == client.address {
+                // the owner is omitted from the topics
+                assert_eq!(topics.len(), 2);
+                events.push_back((Symbol::try_from_val(&env, &topics.get(1).unwrap()).unwrap(), data));
+            }
+        }
+        events
+    };
+
+    let (subscription_id, _, activation_fee) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let (name, data) = contract_events().last().unwrap();
+    assert_eq!(name, symbol_short!("created"));
+    let event = CreatedEvent {
+        subscription_id,
+        subscription: EventSubscription::Omitted,
+        activation_fee,
+        event_seq: 1,
+    };
+    assert_eq!(CreatedEvent::try_from_val(&env, &data).unwrap(), event);
+
+    client.deposit(&owner, &subscription_id, &50);
+    let (name, data) = contract_events().last().unwrap();
+    assert_eq!(name, symbol_short!("deposited"));
+    let event = DepositedEvent {
+        subscription_id,
+        subscription: EventSubscription::Omitted,
+        amount: 50,
+        deposit_seq: 1,
+        from: EventAccount::Omitted,
+        event_seq: 2,
+    };
+    assert_eq!(DepositedEvent::try_from_val(&env, &data).unwrap(), event);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    let (name, data) = contract_events().last().unwrap();
+    assert_eq!(name, symbol_short!("charged"));
+    let event = ChargedEvent {
+        timestamp: 86400 * 1000,
+        subscription_id,
+        amount: 100,
+        // the suspension is published first
+        event_seq: 4,
+    };
+    assert_eq!(ChargedEvent::try_from_val(&env, &data).unwrap(), event);
+}
+
+#[test]
+fn test_repeated_cancel() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    env.as_contract(&client.address, || {
+        assert!(!is_cancelled(&env, subscription_id));
+    });
+
+    client.cancel(&subscription_id);
+    // a repeated cancel is reported as such rather than as a missing subscription
+    env.as_contract(&client.address, || {
+        assert!(is_cancelled(&env, subscription_id));
+        assert!(!is_cancelled(&env, 0));
+        assert!(!is_cancelled(&env, subscription_id + 1));
+    });
+}
+
+#[test]
+fn test_asset_interval() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    client.set_charge_interval_bounds(&(DAY / 2), &(DAY * 7));
+    let params = subscription_params(&env, &owner, 10);
+    assert_eq!(client.asset_interval(&params.base.asset), None);
+
+    // the global default applies without an asset default
+    let (_, subscription, _) = client.create_subscription(&params, &300);
+    assert_eq!(subscription.charge_interval_ms, DAY);
+
+    // the base asset default applies without an explicit interval
+    client.set_asset_interval(&params.base.asset, &(DAY * 7));
+    client.set_asset_interval(&params.quote.asset, &(DAY / 2));
+    assert_eq!(client.asset_interval(&params.base.asset), Some(DAY * 7));
+    let (_, subscription, _) = client.create_subscription(&params, &300);
+    assert_eq!(subscription.charge_interval_ms, DAY * 7);
+
+    // an explicit interval takes precedence
+    let mut params = params;
+    params.charge_interval_ms = Some(DAY * 2);
+    let (_, subscription, _) = client.create_subscription(&params, &300);
+    assert_eq!(subscription.charge_interval_ms, DAY * 2);
+}
+
+#[test]
+fn test_withdraw_all_dust() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let token = TokenClient::new(&env, &config.token);
+    let mut suspended_ids = Vec::new(&env);
+    for _ in 0..3 {
+        let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &350);
+        suspended_ids.push_back(subscription_id);
+    }
+    let (active_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &suspended_ids);
+    let balance = token.balance(&owner);
+    let tvl = client.tvl();
+
+    assert_eq!(client.withdraw_all_dust(&owner), 50 * 3);
+    assert_eq!(token.balance(&owner), balance + 150);
+    assert_eq!(client.tvl(), tvl - 150);
+    for subscription_id in suspended_ids.iter() {
+        let subscription = client.get_subscription(&subscription_id);
+        assert_eq!((subscription.status, subscription.balance), (SubscriptionStatus::Suspended, 0));
+    }
+    // active subscriptions are left intact
+    assert_eq!(client.get_subscription(&active_id).balance, 100);
+    assert_eq!(client.withdraw_all_dust(&owner), 0);
+}
+
+#[test]
+fn test_webhook_size_boundary() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    env.as_contract(&client.address, || {
+        let mut webhook = Bytes::from_array(&env, &[0; MAX_WEBHOOK_SIZE as usize]);
+        assert!(is_valid_webhook_size(&env, &webhook));
+        webhook.push_back(0);
+        assert!(!is_valid_webhook_size(&env, &webhook));
+    });
+
+    // a webhook of exactly the maximum size is stored and published intact
+    let (subscription_id, subscription, _) =
+        client.create_subscription(&subscription_params(&env, &owner, MAX_WEBHOOK_SIZE as usize), &1000);
+    assert_eq!(subscription.webhook.len(), MAX_WEBHOOK_SIZE);
+    assert_eq!(client.get_subscription(&subscription_id).webhook.len(), MAX_WEBHOOK_SIZE);
+    client.set_webhook(&subscription_id, &Bytes::from_array(&env, &[1; MAX_WEBHOOK_SIZE as usize]), &None);
+    assert_eq!(client.get_subscription(&subscription_id).webhook.len(), MAX_WEBHOOK_SIZE);
+}
+
+#[test]
+fn test_force_cancel() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let compliance = Address::generate(&env);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let owner_balance = token.balance(&owner);
+
+    client.force_cancel(&subscription_id, &compliance, &7);
+    assert_eq!(token.balance(&compliance), subscription.balance as i128);
+    assert_eq!(token.balance(&owner), owner_balance);
+    assert_eq!(client.find_subscription(&subscription_id), None);
+    assert_eq!(client.tvl(), 0);
+
+    let topics: Vec<Val> = (REFLECTOR, Symbol::new(&env, FORCE_CANCELLED), owner.clone()).into_val(&env);
+    let (_, event_topics, data) = env.events().all().last().unwrap();
+    assert_eq!(event_topics, topics);
+    let event = ForceCancelledEvent {
+        subscription_id,
+        refund_to: compliance,
+        amount: subscription.balance,
+        reason: 7,
+    };
+    assert_eq!(ForceCancelledEvent::try_from_val(&env, &data).unwrap(), event);
+}
+
+#[test]
+fn test_admin_transfer() {
+    let (env, client, config) = init_contract_with_admin();
+    let new_admin = Address::generate(&env);
+    assert_eq!(client.pending_admin(), None);
+
+    client.propose_admin(&new_admin);
+    assert_eq!(client.pending_admin(), Some(new_admin.clone()));
+    assert_eq!(client.admin(), Some(config.admin.clone()));
+
+    client.accept_admin();
+    // the proposed admin has to authorize the handover
+    assert_eq!(env.auths()[0].0, new_admin);
+    assert_eq!(client.admin(), Some(new_admin.clone()));
+    assert_eq!(client.pending_admin(), None);
+
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(topics, (REFLECTOR, symbol_short!("admin")).into_val(&env));
+    let event = AdminChangedEvent {
+        previous_admin: config.admin,
+        admin: new_admin,
+    };
+    assert_eq!(AdminChangedEvent::try_from_val(&env, &data).unwrap(), event);
+}
+
+#[test]
+fn test_source_cap() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let params = subscription_params(&env, &owner, 10);
+    let source = params.base.source.clone();
+    assert_eq!(client.source_cap(&source), None);
+    client.set_source_cap(&source, &2);
+    assert_eq!(client.source_cap(&source), Some(2));
+
+    let (first_id, _, _) = client.create_subscription(&params, &300);
+    client.create_subscription(&params, &300);
+    env.as_contract(&client.address, || {
+        assert_eq!(env.get_source_count(&source), 2);
+        assert!(exceeds_source_cap(&env, &source));
+        // the quote source is not capped
+        assert!(!exceeds_source_cap(&env, &params.quote.source));
+    });
+
+    // cancelling releases the reference
+    client.cancel(&first_id);
+    env.as_contract(&client.address, || {
+        assert!(!exceeds_source_cap(&env, &source));
+    });
+    client.create_subscription(&params, &300);
+}
+
+#[test]
+fn test_update_webhook() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let mut params = subscription_params(&env, &owner, 10);
+    params.webhook_expires_at = Some(3600 * 1000);
+    let (subscription_id, subscription, _) = client.create_subscription(&params, &300);
+
+    set_timestamp(&env, 60);
+    let webhook = Bytes::from_array(&env, &[1; 20]);
+    client.update_webhook(&subscription_id, &webhook);
+    let updated = client.get_subscription(&subscription_id);
+    assert_eq!(updated.webhook, webhook);
+    // the expiration, balance and billing anchor are kept
+    assert_eq!(updated.webhook_expires_at, subscription.webhook_expires_at);
+    assert_eq!(updated.balance, subscription.balance);
+    assert_eq!(updated.updated, subscription.updated);
+
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(topics, (REFLECTOR, symbol_short!("updated"), owner).into_val(&env));
+    assert_eq!(UpdatedEvent::try_from_val(&env, &data).unwrap(), UpdatedEvent { subscription_id });
+}
+
+#[test]
+fn test_is_admin() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, SubscriptionContract);
+    let uninitialized = SubscriptionContractClient::new(&env, &contract_id);
+    assert!(!uninitialized.is_admin(&Address::generate(&env)));
+
+    let (env, client, config) = init_contract_with_admin();
+    assert!(client.is_admin(&config.admin));
+    assert!(!client.is_admin(&Address::generate(&env)));
+}
+
+#[test]
+fn test_update_trigger_params() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    client.update_trigger_params(&subscription_id, &25, &60);
+    let updated = client.get_subscription(&subscription_id);
+    assert_eq!((updated.threshold, updated.threshold_up, updated.threshold_down), (25, 25, 25));
+    assert_eq!(updated.heartbeat, 60);
+    assert_eq!((updated.balance, updated.updated), (subscription.balance, subscription.updated));
+
+    // the creation validation applies
+    env.as_contract(&client.address, || {
+        assert!(!is_valid_heartbeat(&env, MIN_HEARTBEAT - 1));
+        assert!(is_valid_heartbeat(&env, MIN_HEARTBEAT));
+    });
+    assert!(!is_valid_threshold(0));
+    assert!(!is_valid_threshold(MAX_THRESHOLD + 1));
+}
+
+#[test]
+fn test_pre_suspend_warning() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    client.set_pre_suspend_lead_days(&2);
+    assert_eq!(client.pre_suspend_lead_days(), 2);
+    // 350 covers three daily fees, the suspension is projected at the third charge
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &550);
+
+    let topics: Vec<Val> = (REFLECTOR, Symbol::new(&env, PRE_SUSPEND_WARNING), owner.clone()).into_val(&env);
+    let mut warnings = Vec::new(&env);
+    for day in 1..=3u64 {
+        set_timestamp(&env, 86400 * day);
+        client.charge(&config.admin, &vec![&env, subscription_id]);
+    }
+    for (_, event_topics, data) in env.events().all().iter() {
+        if event_topics == topics {
+            warnings.push_back(PreSuspendWarningEvent::try_from_val(&env, &data).unwrap());
+        }
+    }
+    let event = PreSuspendWarningEvent {
+        subscription_id,
+        suspends_at: 3 * DAY,
+    };
+    assert_eq!(warnings, vec![&env, event]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+
+    // the deposit resets the warning
+    client.reactivate(&owner, &subscription_id, &200);
+    assert!(!client.get_subscription(&subscription_id).warned);
+}
+
+#[test]
+fn test_get_subscriptions() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let params = subscription_params(&env, &owner, 10);
+    for _ in 0..5 {
+        client.create_subscription(&params, &300);
+    }
+    client.cancel(&3);
+
+    let page = client.get_subscriptions(&0, &3);
+    let mut ids = Vec::new(&env);
+    for (id, _) in page.iter() {
+        ids.push_back(id);
+    }
+    assert_eq!(ids, vec![&env, 1, 2, 4]);
+    assert_eq!(page.get(2).unwrap().1, client.get_subscription(&4));
+
+    // the next page resumes after the last returned ID
+    let page = client.get_subscriptions(&5, &3);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap().0, 5);
+    assert_eq!(client.get_subscriptions(&6, &3).len(), 0);
+}
+
+#[test]
+fn test_balance_by_status() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 3000);
+    let scan = || {
+        let (mut active, mut suspended) = (0, 0);
+        for subscription_id in 1..=client.last_id() {
+            match client.find_subscription(&subscription_id) {
+                Some(subscription) if subscription.status == SubscriptionStatus::Active => active += subscription.balance,
+                Some(subscription) if subscription.status == SubscriptionStatus::Suspended => suspended += subscription.balance,
+                _ => {}
+            }
+        }
+        (active, suspended)
+    };
+    for amount in [350, 350, 350, 500, 400] {
+        client.create_subscription(&subscription_params(&env, &owner, 10), &amount);
+    }
+    client.hibernate(&5);
+    assert_eq!(client.balance_by_status(), (750, 0));
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, 1, 2, 4]);
+    assert_eq!(client.balance_by_status(), (350, 100));
+    assert_eq!(client.balance_by_status(), scan());
+
+    client.reactivate(&owner, &1, &300);
+    client.withdraw_all_dust(&owner);
+    client.cancel(&3);
+    client.wake(&5);
+    assert_eq!(client.balance_by_status(), (550, 0));
+    assert_eq!(client.balance_by_status(), scan());
+}
+
+#[test]
+fn test_active_count() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    for amount in [350, 500, 500] {
+        client.create_subscription(&subscription_params(&env, &owner, 10), &amount);
+    }
+    assert_eq!((client.last_id(), client.active_count()), (3, 3));
+
+    client.cancel(&3);
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, 1, 2]);
+    // the cancelled ID is not reused
+    assert_eq!((client.last_id(), client.active_count()), (3, 1));
+
+    client.reactivate(&owner, &1, &300);
+    assert_eq!(client.active_count(), 2);
+}
+
+#[test]
+fn test_trigger_history() {
+    let (env, client, _) = init_contract_with_admin();
+    assert_eq!(client.trigger_history(&5).len(), 0);
+    for timestamp in 1..=12u64 {
+        client.trigger(&timestamp, &BytesN::from_array(&env, &[timestamp as u8; 32]));
+    }
+    // only the most recent triggers are kept
+    let history = client.trigger_history(&100);
+    assert_eq!(history.len(), 10);
+    assert_eq!(history.first().unwrap().0, 3);
+
+    let history = client.trigger_history(&2);
+    let expected = vec![
+        &env,
+        (11u64, BytesN::from_array(&env, &[11; 32])),
+        (12u64, BytesN::from_array(&env, &[12; 32])),
+    ];
+    assert_eq!(history, expected);
+}
+
+#[test]
+fn test_paused() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    assert!(!client.is_paused());
+
+    client.set_paused(&true);
+    assert!(client.is_paused());
+    // deposits, creation and charges are rejected by the guard
+    env.as_contract(&client.address, || {
+        assert!(validate_not_paused(&env) == Err(Error::ContractPaused));
+    });
+    // owners can still exit
+    assert_eq!(client.get_subscription(&subscription_id).balance, 100);
+    client.cancel(&subscription_id);
+
+    client.set_paused(&false);
+    env.as_contract(&client.address, || {
+        assert!(validate_not_paused(&env).is_ok());
+    });
+    client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+}
+
+#[test]
+fn test_merge_subscriptions() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    let params = subscription_params(&env, &owner, 10);
+    let (keep_id, _, _) = client.create_subscription(&params, &300);
+    let (merge_id, _, _) = client.create_subscription(&params, &400);
+    let owner_balance = token.balance(&owner);
+
+    assert_eq!(client.merge_subscriptions(&keep_id, &merge_id), 300);
+    assert_eq!(client.get_subscription(&keep_id).balance, 300);
+    assert_eq!(client.find_subscription(&merge_id), None);
+    // the balance is moved, not refunded
+    assert_eq!(token.balance(&owner), owner_balance);
+    assert_eq!(client.tvl(), 300);
+    assert_eq!(client.active_count(), 1);
+    assert_eq!(client.balance_by_status(), (300, 0));
+
+    let topics: Vec<Val> = (REFLECTOR, symbol_short!("merged"), owner.clone()).into_val(&env);
+    let (_, event_topics, data) = env.events().all().last().unwrap();
+    assert_eq!(event_topics, topics);
+    let event = MergedEvent {
+        subscription_id: keep_id,
+        merged_id: merge_id,
+        amount: 200,
+    };
+    assert_eq!(MergedEvent::try_from_val(&env, &data).unwrap(), event);
+}
+
+#[test]
+fn test_withdraw() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &550);
+    let owner_balance = token.balance(&owner);
+    let fee = env.as_contract(&client.address, || calc_fee(&env, &subscription.webhook));
+
+    assert_eq!(client.withdraw(&subscription_id, &100), 250);
+    assert_eq!(token.balance(&owner), owner_balance + 100);
+    assert_eq!(client.tvl(), 250);
+    // the billing anchor is kept
+    assert_eq!(client.get_subscription(&subscription_id).updated, subscription.updated);
+
+    let subscription = client.get_subscription(&subscription_id);
+    env.as_contract(&client.address, || {
+        assert!(!exceeds_withdrawable(&env, &subscription, 250 - fee));
+        assert!(exceeds_withdrawable(&env, &subscription, 250 - fee + 1));
+    });
+    client.withdraw(&subscription_id, &(250 - fee));
+    assert_eq!(client.get_subscription(&subscription_id).balance, fee);
+}
+
+#[test]
+fn test_set_balance_with_proof() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    let subscription = client.get_subscription(&subscription_id);
+    env.as_contract(&client.address, || {
+        assert!(validate_balance_correction(&subscription, 90) == Err(Error::BalanceMismatch));
+        assert!(validate_balance_correction(&subscription, 100).is_ok());
+    });
+
+    client.set_balance_with_proof(&subscription_id, &80, &100);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 80);
+    assert_eq!(client.tvl(), 80);
+    assert_eq!(client.balance_by_status(), (80, 0));
+
+    let topics: Vec<Val> = (REFLECTOR, symbol_short!("adjusted"), owner.clone()).into_val(&env);
+    let (_, event_topics, data) = env.events().all().last().unwrap();
+    assert_eq!(event_topics, topics);
+    let event = AdjustedEvent {
+        subscription_id,
+        previous_balance: 100,
+        balance: 80,
+    };
+    assert_eq!(AdjustedEvent::try_from_val(&env, &data).unwrap(), event);
+}
+
+#[test]
+fn test_transfer_ownership() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let new_owner = Address::generate(&env);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    client.transfer_ownership(&subscription_id, &new_owner);
+    let transferred = client.get_subscription(&subscription_id);
+    assert_eq!(transferred.owner, new_owner);
+    assert_eq!((transferred.balance, transferred.status), (subscription.balance, subscription.status));
+    env.as_contract(&client.address, || {
+        assert_eq!(env.get_owner_subscriptions(&owner).len(), 0);
+        assert_eq!(env.get_owner_subscriptions(&new_owner), vec![&env, subscription_id]);
+    });
+
+    let topics: Vec<Val> = (REFLECTOR, symbol_short!("owner"), owner.clone()).into_val(&env);
+    let (_, event_topics, data) = env.events().all().last().unwrap();
+    assert_eq!(event_topics, topics);
+    let event = OwnerChangedEvent {
+        subscription_id,
+        previous_owner: owner,
+        owner: new_owner.clone(),
+    };
+    assert_eq!(OwnerChangedEvent::try_from_val(&env, &data).unwrap(), event);
+
+    // the refund goes to the new owner
+    client.cancel(&subscription_id);
+    assert_eq!(token.balance(&new_owner), subscription.balance as i128);
+}
+
+#[test]
+fn test_transfer_ownership_to_same_owner() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    env.as_contract(&client.address, || {
+        let subscription = env.get_subscription(subscription_id).unwrap();
+        assert!(validate_ownership_transfer(&subscription, &owner) == Err(Error::SameOwner));
+        assert!(validate_ownership_transfer(&subscription, &Address::generate(&env)).is_ok());
+    });
+}
+
+#[test]
+fn test_charge_carries_remainder() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &600);
+    let ids = vec![&env, subscription_id];
+
+    set_timestamp(&env, 86400 + 43200);
+    client.charge(&config.admin, &ids);
+    set_timestamp(&env, 86400 + 64800);
+    client.charge(&config.admin, &ids);
+    let charged = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.balance - charged.balance, 100);
+    // the half day elapsed after the billed day is kept for the next charge
+    assert_eq!(charged.updated, subscription.updated + DAY);
+
+    set_timestamp(&env, 2 * 86400);
+    client.charge(&config.admin, &ids);
+    assert_eq!(client.get_subscription(&subscription_id).balance, subscription.balance - 200);
+}
+
+#[test]
+fn test_charge_empty_balance() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    client.set_balance_with_proof(&subscription_id, &0, &100);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    let charged: Vec<Val> = (REFLECTOR, symbol_short!("charged"), owner.clone()).into_val(&env);
+    let suspended: Vec<Val> = (REFLECTOR, symbol_short!("suspended"), owner.clone()).into_val(&env);
+    let events = env.events().all();
+    assert!(!events.iter().any(|(_, topics, _)| topics == charged));
+    assert!(events.iter().any(|(_, topics, _)| topics == suspended));
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+}
+
+#[test]
+fn test_cancel_suspended() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    // 350 leaves 50 after the first daily fee, less than the next one
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &350);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!((subscription.status, subscription.balance), (SubscriptionStatus::Suspended, 50));
+
+    let owner_balance = token.balance(&owner);
+    client.cancel(&subscription_id);
+    assert_eq!(token.balance(&owner), owner_balance + 50);
+    assert_eq!(client.find_subscription(&subscription_id), None);
+    assert_eq!(client.balance_by_status(), (0, 0));
+}
+
+#[test]
+fn test_charged_amount() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &550);
+    let topics: Vec<Val> = (REFLECTOR, symbol_short!("charged"), owner.clone()).into_val(&env);
+    let last_charged = || {
+        let (_, _, data) = env.events().all().iter().filter(|(_, event_topics, _)| *event_topics == topics).last().unwrap();
+        ChargedEvent::try_from_val(&env, &data).unwrap().amount
+    };
+
+    set_timestamp(&env, 2 * 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(last_charged(), 200);
+
+    // five more days are clamped to the remaining balance
+    client.deposit(&owner, &subscription_id, &100);
+    set_timestamp(&env, 7 * 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(last_charged(), 250);
+}
+
+#[test]
+fn test_get_owner_subscriptions() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let other = funded_owner(&env, &config, 1000);
+    let (first_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let (other_id, _, _) = client.create_subscription(&subscription_params(&env, &other, 10), &300);
+    let (second_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    assert_eq!(client.get_owner_subscriptions(&owner), vec![&env, first_id, second_id]);
+    assert_eq!(client.get_owner_subscriptions(&other), vec![&env, other_id]);
+    client.cancel(&first_id);
+    assert_eq!(client.get_owner_subscriptions(&owner), vec![&env, second_id]);
+
+    env.as_contract(&client.address, || {
+        assert!(!exceeds_owner_limit(&env, &owner));
+        let mut owner_subscriptions = Vec::new(&env);
+        for subscription_id in 0..MAX_OWNER_SUBSCRIPTIONS as u64 {
+            owner_subscriptions.push_back(subscription_id);
+        }
+        env.set_owner_subscriptions(&owner, &owner_subscriptions);
+        assert!(exceeds_owner_limit(&env, &owner));
+    });
+}
+
+#[test]
+fn test_min_heartbeat() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.limits().1, MIN_HEARTBEAT);
+    env.as_contract(&client.address, || {
+        assert!(!is_valid_heartbeat(&env, 2));
+    });
+
+    client.set_min_heartbeat(&1);
+    assert_eq!(client.limits().1, 1);
+    let mut params = subscription_params(&env, &owner, 10);
+    params.heartbeat = 2;
+    let (_, subscription, _) = client.create_subscription(&params, &300);
+    assert_eq!(subscription.heartbeat, 2);
+}
+
+#[test]
+fn test_activation_refund() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let token = TokenClient::new(&env, &config.token);
+    client.set_activation_refund_percent(&50);
+    let topics: Vec<Val> = (REFLECTOR, symbol_short!("cancelled"), owner.clone()).into_val(&env);
+    let last_cancelled = || {
+        let (_, _, data) = env.events().all().iter().filter(|(_, event_topics, _)| *event_topics == topics).last().unwrap();
+        CancelledEvent::try_from_val(&env, &data).unwrap().activation_refund
+    };
+
+    // half of the 200 activation fee is held back from the burn
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    assert_eq!((subscription.activation_fee, subscription.refund_hold), (200, 100));
+    assert_eq!(token.balance(&client.address), 200);
+    let owner_balance = token.balance(&owner);
+    client.cancel(&subscription_id);
+    assert_eq!(token.balance(&owner), owner_balance + 100 + 100);
+    assert_eq!(last_cancelled(), 100);
+    assert_eq!(token.balance(&client.address), 0);
+
+    // the percentage is applied to the paid activation fee, the unrefunded part of the hold is burned
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    client.set_fee(&200);
+    client.set_activation_refund_percent(&30);
+    client.cancel(&subscription_id);
+    assert_eq!(last_cancelled(), 60);
+    assert_eq!((token.balance(&client.address), client.tvl()), (0, 0));
+
+    // raising the percentage doesn't increase the hold of existing subscriptions
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    client.set_activation_refund_percent(&100);
+    client.cancel(&subscription_id);
+    assert_eq!(last_cancelled(), 120);
+    assert!(client.verify_solvency());
+
+    // no refund after the grace period, the hold is burned on cancellation
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    set_timestamp(&env, 3600);
+    client.cancel(&subscription_id);
+    assert_eq!(last_cancelled(), 0);
+    assert_eq!((token.balance(&client.address), client.tvl()), (0, 0));
+
+    // the grace period runs from the activation, and the expired hold is burned by the charge
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &1000);
+    set_timestamp(&env, 3600 + 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    let charged = client.get_subscription(&subscription_id);
+    assert_eq!((charged.balance, charged.refund_hold), (400, 0));
+    assert_eq!((token.balance(&client.address), client.tvl()), (400, 400));
+}
+
+#[test]
+fn test_payment_token() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let other_token = env.register_stellar_asset_contract(config.admin.clone());
+    StellarAssetClient::new(&env, &other_token).mint(&owner, &1000);
+    let token = TokenClient::new(&env, &config.token);
+    let other = TokenClient::new(&env, &other_token);
+    client.set_token_allowed(&other_token, &true);
+
+    let (default_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let mut params = subscription_params(&env, &owner, 10);
+    params.token = PaymentToken::Custom(other_token.clone());
+    let (other_id, subscription, _) = client.create_subscription(&params, &400);
+    assert_eq!(subscription.token, other_token);
+    assert_eq!((token.balance(&client.address), other.balance(&client.address)), (300, 200));
+    // the contract token totals don't include other tokens
+    assert_eq!(client.tvl(), 300);
+    assert_eq!(client.balance_by_status(), (300, 0));
+    assert_eq!(client.projected_daily_revenue(), 100);
+
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, default_id, other_id]);
+    assert_eq!((token.balance(&client.address), other.balance(&client.address)), (200, 100));
+    env.as_contract(&client.address, || {
+        assert_eq!(env.get_tvl(&other_token), 100);
+    });
+    assert_eq!(client.balance_by_status(), (200, 0));
+    assert_eq!(client.projected_daily_revenue(), 100);
+
+    client.cancel(&other_id);
+    assert_eq!(other.balance(&owner), 1000 - 400 + 100);
+    assert_eq!(token.balance(&client.address), 200);
+}
+
+#[test]
+fn test_payment_token_allowlist() {
+    let (env, client, config) = init_contract_with_admin();
+    let other_token = env.register_stellar_asset_contract(config.admin.clone());
+    assert!(client.is_token_allowed(&config.token));
+    assert!(!client.is_token_allowed(&other_token));
+
+    env.as_contract(&client.address, || {
+        assert!(validate_payment_token(&env, &PaymentToken::Default) == Ok(config.token.clone()));
+        assert!(validate_payment_token(&env, &PaymentToken::Custom(config.token.clone())) == Ok(config.token.clone()));
+        assert!(validate_payment_token(&env, &PaymentToken::Custom(other_token.clone())) == Err(Error::TokenNotAllowed));
+    });
+
+    client.set_token_allowed(&other_token, &true);
+    assert!(client.is_token_allowed(&other_token));
+    client.set_token_allowed(&other_token, &false);
+    assert!(!client.is_token_allowed(&other_token));
+}
+
+#[test]
+fn test_create_subscriptions() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let mut params = Vec::new(&env);
+    for _ in 0..3 {
+        params.push_back(subscription_params(&env, &owner, 10));
+    }
+    let amounts = vec![&env, 300, 400, 500];
+
+    let created = client.create_subscriptions(&params, &amounts);
+    assert_eq!(created.len(), 3);
+    for (index, (subscription_id, subscription)) in created.iter().enumerate() {
+        assert_eq!(subscription_id, index as u64 + 1);
+        assert_eq!(subscription.balance, 100 * (index as u64 + 1));
+        assert_eq!(client.get_subscription(&subscription_id), subscription);
+    }
+    assert_eq!(client.tvl(), 600);
+
+    env.as_contract(&client.address, || {
+        assert!(validate_create_batch(&params, &vec![&env, 300]) == Err(Error::BatchLengthMismatch));
+        let mut oversized = Vec::new(&env);
+        for _ in 0..=MAX_CREATE_SIZE {
+            oversized.push_back(subscription_params(&env, &owner, 10));
+        }
+        let amounts = Vec::from_array(&env, [300; MAX_CREATE_SIZE as usize + 1]);
+        assert!(validate_create_batch(&oversized, &amounts) == Err(Error::BatchTooLarge));
+    });
+}
+
+#[test]
+fn test_storage_ttl() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let set_sequence = |sequence_number: u32| {
+        let ledger_info = env.ledger().get();
+        env.ledger().set(LedgerInfo {
+            sequence_number,
+            ..ledger_info
+        });
+    };
+    let instance_ttl = || env.as_contract(&client.address, || env.storage().instance().get_ttl());
+    let subscription_ttl = || env.as_contract(&client.address, || env.storage().persistent().get_ttl(&subscription_id));
+
+    // the deposit extends the subscription entry according to the new balance
+    set_sequence(17280);
+    let ttl = subscription_ttl();
+    client.deposit(&owner, &subscription_id, &200);
+    assert!(subscription_ttl() > ttl);
+
+    // admin operations extend the instance storage once it drops below the threshold
+    assert_eq!(client.instance_ttl(), (17280 * 7, 17280 * 30));
+    env.as_contract(&client.address, || {
+        let max_ttl = env.storage().max_ttl();
+        assert!(validate_instance_ttl(&env, 17280 * 10, 17280 * 2) == Err(Error::InvalidTtl));
+        assert!(validate_instance_ttl(&env, 17280, max_ttl + 1) == Err(Error::InvalidTtl));
+        assert!(validate_instance_ttl(&env, 17280 * 2, 17280 * 10).is_ok());
+    });
+    client.set_instance_ttl(&(17280 * 2), &(17280 * 10));
+    // the extension never shortens the TTL
+    let ttl = instance_ttl();
+    assert!(ttl > 17280 * 10);
+    // one day before the expiration
+    set_sequence(ttl);
+    assert_eq!(instance_ttl(), 17280);
+    client.set_min_reserve(&0);
+    assert_eq!(instance_ttl(), 17280 * 10);
+}
+
+#[test]
+fn test_charge_result() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let (active_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let (suspended_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &350);
+    set_timestamp(&env, 86400);
+    // created after the others, not due yet
+    let (not_due_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    let result = client.charge(&config.admin, &vec![&env, active_id, suspended_id, not_due_id, 100]);
+    let expected = vec![
+        &env,
+        (active_id, 100, SubscriptionStatus::Active),
+        (suspended_id, 100, SubscriptionStatus::Suspended),
+    ];
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_asset_pair() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let mut params = subscription_params(&env, &owner, 10);
+    params.quote = TickerAsset {
+        asset: params.base.asset.clone(),
+        source: params.base.source.clone(),
+    };
+    env.as_contract(&client.address, || {
+        assert!(!is_valid_pair(&params.base, &params.quote));
+    });
+
+    // the same asset from another source is allowed
+    params.quote.source = String::from_str(&env, "source2");
+    env.as_contract(&client.address, || {
+        assert!(is_valid_pair(&params.base, &params.quote));
+    });
+    let (_, subscription, _) = client.create_subscription(&params, &300);
+    assert_eq!(subscription.base.asset, subscription.quote.asset);
+}
+
+#[test]
+fn test_resumed_event() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &350);
+    let contract_events = || {
+        let mut names = Vec::new(&env);
+        for (contract, topics, _) in env.events().all().iter() {
+            if contract == client.address {
+                names.push_back(Symbol::try_from_val(&env, &topics.get(1).unwrap()).unwrap());
+            }
+        }
+        names
+    };
+
+    client.deposit(&owner, &subscription_id, &100);
+    assert!(!contract_events().contains(symbol_short!("resumed")));
+
+    set_timestamp(&env, 2 * 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+    client.reactivate(&owner, &subscription_id, &300);
+    let names = contract_events();
+    assert_eq!(names.slice(names.len() - 2..), vec![&env, symbol_short!("deposited"), symbol_short!("resumed")]);
+    let (_, _, data) = env.events().all().last().unwrap();
+    let event = ResumedEvent {
+        subscription_id,
+        activation_fee: 200,
+    };
+    assert_eq!(ResumedEvent::try_from_val(&env, &data).unwrap(), event);
+}
+
+#[test]
+fn test_force_cancel_to_owner() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let owner_balance = token.balance(&owner);
+
+    client.force_cancel(&subscription_id, &owner, &1);
+    // only the admin authorized the termination
+    assert_eq!(env.auths().len(), 1);
+    assert_eq!(env.auths()[0].0, config.admin);
+    assert_eq!(token.balance(&owner), owner_balance + subscription.balance as i128);
+    assert_eq!(client.find_subscription(&subscription_id), None);
+    assert_eq!(client.get_owner_subscriptions(&owner).len(), 0);
+    env.as_contract(&client.address, || {
+        assert!(is_cancelled(&env, subscription_id));
+    });
+}
+
+#[test]
+fn test_has_subscription() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let (cancelled_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    client.cancel(&cancelled_id);
+
+    assert!(client.has_subscription(&subscription_id));
+    assert!(!client.has_subscription(&100));
+    assert!(!client.has_subscription(&cancelled_id));
+}
+
+#[test]
+fn test_max_per_owner() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let other = funded_owner(&env, &config, 1000);
+    assert_eq!(client.max_per_owner(), 0);
+    client.set_max_per_owner(&2);
+    assert_eq!(client.max_per_owner(), 2);
+
+    client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    env.as_contract(&client.address, || {
+        assert!(!exceeds_owner_limit(&env, &owner));
+    });
+    let (second_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    env.as_contract(&client.address, || {
+        assert!(exceeds_owner_limit(&env, &owner));
+        assert!(!exceeds_owner_limit(&env, &other));
+    });
+
+    // cancelling frees a slot, lifting the limit removes it
+    client.cancel(&second_id);
+    client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    client.set_max_per_owner(&0);
+    client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    assert_eq!(client.get_owner_subscriptions(&owner).len(), 3);
+}
+
+#[test]
+fn test_prorated_first_charge() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    set_timestamp(&env, 3600);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    client.set_prorate_charge(&true);
+
+    // the first partial day is billed from the activation time
+    set_timestamp(&env, 3600 + 21600);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.balance, 275);
+    assert_eq!(subscription.updated, (3600 + 21600) * 1000);
+}
+
+#[test]
+fn test_tvl_matches_balances() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let (first_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let (second_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    client.deposit(&owner, &first_id, &150);
+    client.deposit(&owner, &second_id, &20);
+
+    // the second subscription can't cover the second day and gets suspended
+    set_timestamp(&env, 86400 * 2);
+    client.charge(&config.admin, &vec![&env, first_id, second_id]);
+    assert_eq!(client.get_subscription(&second_id).status, SubscriptionStatus::Suspended);
+
+    let total = client.get_subscription(&first_id).balance + client.get_subscription(&second_id).balance;
+    assert_eq!(client.tvl(), total);
+    let held = TokenClient::new(&env, &config.token).balance(&client.address);
+    assert_eq!(held, total as i128);
+}
+
+#[test]
+fn test_timestamp_overflow() {
+    assert_eq!(to_millis(86400), Some(86400 * 1000));
+    assert_eq!(to_millis(u64::MAX / 1000), Some(u64::MAX / 1000 * 1000));
+    assert_eq!(to_millis(u64::MAX / 1000 + 1), None);
+    assert_eq!(to_millis(u64::MAX), None);
+}
+
+#[test]
+fn test_fee_changed_event() {
+    let (env, client, _) = init_contract_with_admin();
+    client.set_fee(&150);
+
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(topics, (REFLECTOR, symbol_short!("fee")).into_val(&env));
+    let event = FeeChangedEvent { previous_fee: 100, fee: 150 };
+    assert_eq!(FeeChangedEvent::try_from_val(&env, &data).unwrap(), event);
+}
+
+#[test]
+fn test_get_subscriptions_by_ids() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (active_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    let (cancelled_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    client.cancel(&cancelled_id);
+
+    let subscriptions = client.get_subscriptions_by_ids(&vec![&env, active_id, cancelled_id, 100]);
+    assert_eq!(
+        subscriptions,
+        vec![
+            &env,
+            (active_id, Some(client.get_subscription(&active_id))),
+            (cancelled_id, None),
+            (100, None)
+        ]
+    );
+}
+
+#[test]
+fn test_sweep() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let (funded_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &400);
+    let (underfunded_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+    let (outside_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+
+    set_timestamp(&env, 86400);
+    let suspended = client.sweep(&funded_id, &2);
+    assert_eq!(suspended, vec![&env, underfunded_id]);
+    assert_eq!(client.get_subscription(&funded_id).status, SubscriptionStatus::Active);
+    assert_eq!(client.get_subscription(&funded_id).balance, 100);
+    // subscriptions beyond the range are left for the next call
+    assert_eq!(client.get_subscription(&outside_id).status, SubscriptionStatus::Active);
+
+    assert_eq!(client.sweep(&outside_id, &100), vec![&env, outside_id]);
+}
+
+#[test]
+fn test_sponsored_deposit_event() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let sponsor = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+
+    client.deposit(&sponsor, &subscription_id, &50);
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(topics, (REFLECTOR, symbol_short!("deposited"), owner).into_val(&env));
+    let event = DepositedEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!((event.amount, event.from), (50, EventAccount::Full(sponsor)));
+}
+
+#[test]
+fn test_max_webhook_size() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.limits().0, MAX_WEBHOOK_SIZE);
+
+    client.set_max_webhook_size(&512);
+    assert_eq!(client.limits().0, 512);
+    env.as_contract(&client.address, || {
+        assert!(!is_valid_webhook_size(&env, &Bytes::from_array(&env, &[0; 1024])));
+    });
+    let (_, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 512), &300);
+    assert_eq!(subscription.webhook.len(), 512);
+}
+
+#[test]
+fn test_zero_fee() {
+    let (env, client, _) = init_contract_with_admin();
+    env.as_contract(&client.address, || {
+        assert!(!is_valid_fee(0));
+        assert!(is_valid_fee(1));
+    });
+    client.set_fee(&1);
+    assert_eq!(client.fee(), 1);
+}
+
+#[test]
+fn test_zero_min_heartbeat() {
+    let (env, client, _) = init_contract_with_admin();
+    // the initialization and the setter share the validation
+    env.as_contract(&client.address, || {
+        assert!(!is_valid_min_heartbeat(0));
+        assert!(is_valid_min_heartbeat(1));
+    });
+    client.set_min_heartbeat(&1);
+    assert_eq!(client.limits().1, 1);
+}
+
+#[test]
+fn test_deposit_many() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 2000);
+    let sponsor = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    let (first_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let (second_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let (suspended_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, suspended_id]);
+    assert_eq!(client.get_subscription(&suspended_id).status, SubscriptionStatus::Suspended);
+    let contract_balance = token.balance(&client.address);
+
+    let results = client.deposit_many(&sponsor, &vec![&env, (first_id, 50), (second_id, 60), (suspended_id, 250)]);
+    assert_eq!(results, vec![&env, (50, 0), (60, 0), (50, 200)]);
+    assert_eq!(token.balance(&sponsor), 1000 - 360);
+    assert_eq!(token.balance(&client.address), contract_balance + 160);
+
+    // only the suspended subscription is reactivated, its activation fee is burned
+    assert_eq!(client.get_subscription(&first_id).balance, 350);
+    assert_eq!(client.get_subscription(&second_id).balance, 360);
+    let resumed = client.get_subscription(&suspended_id);
+    assert_eq!(resumed.status, SubscriptionStatus::Active);
+    assert_eq!(resumed.balance, 50);
+    assert_eq!(client.tvl(), 350 + 360 + 50);
+}
+
+#[test]
+fn test_suspend_grace() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.suspend_grace(), 0);
+    client.set_suspend_grace(&(2 * DAY));
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &250);
+    assert_eq!(client.suspension_timestamp(&subscription_id), 2 * DAY);
+
+    // the first underfunded charge starts the grace period instead of suspending
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.status, SubscriptionStatus::Active);
+    assert_eq!(subscription.grace_until, Some(3 * DAY));
+    assert_eq!(client.suspension_timestamp(&subscription_id), 3 * DAY);
+
+    set_timestamp(&env, 86400 * 2);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Active);
+
+    // still underfunded once the grace period is over
+    set_timestamp(&env, 86400 * 3);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.status, SubscriptionStatus::Suspended);
+    assert_eq!(subscription.grace_until, None);
+}
+
+#[test]
+fn test_estimate() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.estimate(&500), (200, 3));
+    assert_eq!(client.estimate(&150), (200, 0));
+
+    // the estimate matches the actual activation fee and the funded days
+    let (subscription_id, _, activation_fee) = client.create_subscription(&subscription_params(&env, &owner, 0), &500);
+    assert_eq!(activation_fee, 200);
+    assert_eq!(client.suspension_timestamp(&subscription_id), 3 * DAY);
+}
+
+#[test]
+fn test_cancel_to() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let cold_wallet = Address::generate(&env);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+
+    client.cancel_to(&subscription_id, &cold_wallet);
+    // the owner authorizes, the refund lands at the payout address
+    assert_eq!(env.auths()[0].0, owner);
+    assert_eq!(token.balance(&cold_wallet), 300);
+    assert_eq!(token.balance(&owner), 500);
+    assert!(!client.has_subscription(&subscription_id));
+    assert_eq!(client.tvl(), 0);
+}
+
+#[test]
+fn test_charge_once_per_ledger() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    let ids = vec![&env, subscription_id];
+
+    set_timestamp(&env, 86400);
+    assert_eq!(client.charge(&config.admin, &ids).len(), 1);
+    assert!(client.charge(&config.admin, &ids).is_empty());
+    assert_eq!(client.get_subscription(&subscription_id).balance, 200);
+
+    // the prorated charge moves the anchor to the current time, leaving nothing to charge again
+    client.set_prorate_charge(&true);
+    set_timestamp(&env, 86400 + 3600);
+    assert_eq!(client.charge(&config.admin, &ids).len(), 1);
+    assert!(client.charge(&config.admin, &ids).is_empty());
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.balance, 196);
+    assert_eq!(subscription.updated, (86400 + 3600) * 1000);
+    env.as_contract(&client.address, || {
+        let mut subscription = subscription.clone();
+        assert_eq!(apply_charge(&env, &mut subscription, (86400 + 3600) * 1000), None);
+        assert_eq!(apply_charge(&env, &mut subscription, 86400 * 1000), None);
+    });
+}
+
+#[test]
+fn test_charge_duplicate_ids() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let token = TokenClient::new(&env, &config.token);
+    let (subscription_id, _, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+    client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+
+    set_timestamp(&env, 86400);
+    let charged = client.charge(&config.admin, &vec![&env, subscription_id, subscription_id]);
+    assert_eq!(charged, vec![&env, (subscription_id, 100, SubscriptionStatus::Active)]);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 200);
+    // the other subscription's custodied balance is not burned
+    assert_eq!(client.tvl(), 200 + 300);
+    assert_eq!(token.balance(&client.address), 200 + 300);
+    assert_eq!(client.balance_by_status(), (200 + 300, 0));
+}
+
+#[test]
+fn test_fee_factor() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    assert_eq!(client.fee_factor(), ACTIVATION_FEE_FACTOR);
+    assert_eq!(client.estimate(&500).0, 200);
+
+    // doubling the factor doubles the minimum deposit
+    client.set_fee_factor(&4);
+    assert_eq!(client.fee_factor(), 4);
+    assert_eq!(client.estimate(&500).0, 400);
+    let (subscription_id, subscription, activation_fee) = client.create_subscription(&subscription_params(&env, &owner, 0), &400);
+    assert_eq!((activation_fee, subscription.balance), (400, 0));
+
+    // the reactivation is charged with the factor as well
+    set_timestamp(&env, 86400);
+    client.charge(&config.admin, &vec![&env, subscription_id]);
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Suspended);
+    client.set_fee_factor(&3);
+    assert_eq!(client.reactivate(&owner, &subscription_id, &350), (50, 300));
+    assert_eq!(client.get_subscription(&subscription_id).status, SubscriptionStatus::Active);
+}
+
+#[test]
+fn test_event_seq() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let topics = |name: Symbol| -> Vec<Val> { (REFLECTOR, name, owner.clone()).into_val(&env) };
+    let last_event = |name: Symbol| {
+        let topics = topics(name);
+        env.events().all().iter().filter(|(_, event_topics, _)| *event_topics == topics).last().unwrap().2
+    };
+
+    let params = subscription_params(&env, &owner, 10);
+    let (subscription_id, _, _) = client.create_subscription(&params, &300);
+    let created_topics: Vec<Val> = (REFLECTOR, symbol_short!("created"), owner.clone(), asset_topic(&env, &params.base.asset)).into_val(&env);
+    let (_, _, data) = env.events().all().iter().filter(|(_, event_topics, _)| *event_topics == created_topics).last().unwrap();
+    let created = CreatedEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(created.event_seq, 1);
+
+    client.deposit(&owner, &subscription_id, &50);
+    let deposited = DepositedEvent::try_from_val(&env, &last_event(symbol_short!("deposited"))).unwrap();
+    assert_eq!(deposited.event_seq, 2);
+
+    client.cancel(&subscription_id);
+    let cancelled = CancelledEvent::try_from_val(&env, &last_event(symbol_short!("cancelled"))).unwrap();
+    assert_eq!(cancelled.event_seq, 3);
+}
+
+#[test]
+fn test_admin_adjust_balance() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let (subscription_id, subscription, _) = client.create_subscription(&subscription_params(&env, &owner, 10), &500);
+
+    assert_eq!(client.admin_adjust_balance(&subscription_id, &50), 350);
+    let adjusted = client.get_subscription(&subscription_id);
+    assert_eq!(adjusted.balance, 350);
+    // the billing anchor is kept
+    assert_eq!(adjusted.updated, subscription.updated);
+    assert_eq!(client.tvl(), 350);
+    let (_, _, data) = env.events().all().last().unwrap();
+    let event = AdjustedEvent {
+        subscription_id,
+        previous_balance: 300,
+        balance: 350,
+    };
+    assert_eq!(AdjustedEvent::try_from_val(&env, &data).unwrap(), event);
+
+    // debits beyond the balance clamp at zero
+    assert_eq!(client.admin_adjust_balance(&subscription_id, &-1000), 0);
+    assert_eq!(client.get_subscription(&subscription_id).balance, 0);
+    assert_eq!(client.tvl(), 0);
+
+    assert_eq!(calc_adjusted_balance(u64::MAX, 1), None);
+    assert_eq!(calc_adjusted_balance(0, i128::MAX), None);
+    assert_eq!(calc_adjusted_balance(10, i128::MIN), Some(0));
+    assert_eq!(calc_adjusted_balance(u64::MAX - 1, 1), Some(u64::MAX));
+}
+
+#[test]
+fn test_created_asset_topic() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    let other = funded_owner(&env, &config, 1000);
+    let btc = symbol_short!("BTC");
+    let mut params = subscription_params(&env, &other, 10);
+    params.base.asset = Asset::Stellar(config.token.clone());
+    client.create_subscription(&subscription_params(&env, &owner, 10), &300);
+    client.create_subscription(&params, &300);
+    client.create_subscription(&subscription_params(&env, &other, 10), &300);
+
+    let created = |owner: &Address, base: Val| {
+        let topics: Vec<Val> = (REFLECTOR, symbol_short!("created"), owner.clone(), base).into_val(&env);
+        env.events().all().iter().filter(|(_, event_topics, _)| *event_topics == topics).count()
+    };
+    assert_eq!(created(&owner, btc.into_val(&env)), 1);
+    assert_eq!(created(&other, btc.into_val(&env)), 1);
+    assert_eq!(created(&other, config.token.into_val(&env)), 1);
+    assert_eq!(created(&owner, config.token.into_val(&env)), 0);
+}
+
+#[test]
+fn test_label() {
+    let (env, client, config) = init_contract_with_admin();
+    let owner = funded_owner(&env, &config, 1000);
+    env.as_contract(&client.address, || {
+        let mut label = Bytes::from_array(&env, &[b'a'; MAX_LABEL_SIZE as usize]);
+        assert!(is_valid_label(&label));
+        label.push_back(b'a');
+        assert!(!is_valid_label(&label));
+    });
+
+    let mut params = subscription_params(&env, &owner, 10);
+    params.label = Bytes::from_array(&env, &[b'a'; MAX_LABEL_SIZE as usize]);
+    let (subscription_id, subscription, _) = client.create_subscription(&params, &300);
+    assert_eq!(subscription.label.len(), MAX_LABEL_SIZE);
+
+    let label = Bytes::from_slice(&env, b"treasury");
+    client.update_label(&subscription_id, &label);
+    assert_eq!(env.auths()[0].0, owner);
+    assert_eq!(client.get_subscription(&subscription_id).label, label);
+    client.update_label(&subscription_id, &Bytes::new(&env));
+    assert!(client.get_subscription(&subscription_id).label.is_empty());
+}
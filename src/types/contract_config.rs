@@ -11,4 +11,6 @@ pub struct ContractConfig {
     pub token: Address,
     // The base fee for the contract.
     pub fee: u64,
+    // The minimum heartbeat in minutes, defaults to 5 minutes.
+    pub min_heartbeat: Option<u32>,
 }
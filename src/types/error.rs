@@ -2,6 +2,7 @@ use soroban_sdk::contracterror;
 
 #[contracterror]
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[allow(clippy::enum_variant_names)]
 // The error codes for the contract.
 pub enum Error {
     // The contract is already initialized.
@@ -21,5 +22,55 @@ pub enum Error {
     // The webhook is too long.
     WebhookTooLong = 7,
     // The subscription status is not valid for the operation.
-    InvalidSubscriptionStatusError = 8
+    InvalidSubscriptionStatusError = 8,
+    // The batch exceeds the maximum number of items.
+    BatchTooLarge = 9,
+    // The deposit was made before the minimum deposit interval elapsed.
+    DepositTooSoon = 10,
+    // The owner is not allowed by the registry.
+    NotAllowed = 11,
+    // The priority exceeds the configured maximum.
+    InvalidPriority = 12,
+    // The total custodied balance would exceed the cap.
+    TvlCapExceeded = 13,
+    // The same pair was cancelled by the owner too recently to be recreated.
+    RecreateCooldown = 14,
+    // The caller is not the owner of the subscription.
+    NotOwner = 15,
+    // Billing is paused.
+    BillingPaused = 16,
+    // The charge interval is out of the configured bounds.
+    InvalidChargeInterval = 17,
+    // The webhook expiration is not in the future.
+    InvalidWebhookExpiration = 18,
+    // The subscription has already been cancelled.
+    AlreadyCancelled = 19,
+    // No admin transfer has been proposed.
+    NoPendingAdmin = 20,
+    // The source is referenced by the maximum number of subscriptions.
+    SourceCapExceeded = 21,
+    // The contract is paused.
+    ContractPaused = 22,
+    // The subscriptions cannot be merged.
+    InvalidMerge = 23,
+    // The subscription balance does not match the expected one.
+    BalanceMismatch = 24,
+    // The owner holds the maximum number of subscriptions.
+    OwnerLimitExceeded = 25,
+    // The batch parameters have different lengths.
+    BatchLengthMismatch = 26,
+    // The base and quote are the same ticker asset.
+    InvalidAssetPair = 27,
+    // The ledger timestamp can't be represented in milliseconds.
+    TimestampOverflow = 28,
+    // The base fee is zero.
+    InvalidFee = 29,
+    // The label exceeds the maximum size.
+    LabelTooLong = 30,
+    // The subscription is transferred to its current owner.
+    SameOwner = 31,
+    // The payment token is not allowed by the admin.
+    TokenNotAllowed = 32,
+    // The TTL threshold exceeds the extension or the extension exceeds the maximum TTL.
+    InvalidTtl = 33
 }
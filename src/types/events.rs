@@ -0,0 +1,266 @@
+use soroban_sdk::{contracttype, Address, BytesN, Symbol};
+
+use super::subscription::Subscription;
+
+// Published events data.
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+// The subscription data attached to the event.
+pub enum EventSubscription {
+    // The data is omitted as the minimal events are enabled.
+    Omitted,
+    // The subscription data.
+    Full(Subscription),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The account attached to the event.
+pub enum EventAccount {
+    // The account is omitted as the minimal events are enabled.
+    Omitted,
+    // The account address.
+    Full(Address),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The admin transfer was accepted.
+pub struct AdminChangedEvent {
+    // The previous admin address.
+    pub previous_admin: Address,
+    // The new admin address.
+    pub admin: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The base fee was changed.
+pub struct FeeChangedEvent {
+    // The previous base fee.
+    pub previous_fee: u64,
+    // The new base fee.
+    pub fee: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The trigger was received.
+pub struct TriggeredEvent {
+    // The trigger timestamp.
+    pub timestamp: u64,
+    // The trigger data hash.
+    pub trigger_hash: BytesN<32>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was created.
+pub struct CreatedEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The subscription data.
+    pub subscription: EventSubscription,
+    // The burned activation fee.
+    pub activation_fee: u64,
+    // The lifecycle event sequence number.
+    pub event_seq: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// Funds were deposited to the subscription.
+pub struct DepositedEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The subscription data.
+    pub subscription: EventSubscription,
+    // The deposited amount.
+    pub amount: u64,
+    // The deposit sequence number.
+    pub deposit_seq: u64,
+    // The account the funds were transferred from, differs from the owner for sponsored deposits.
+    pub from: EventAccount,
+    // The lifecycle event sequence number.
+    pub event_seq: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was charged.
+pub struct ChargedEvent {
+    // The charge timestamp in milliseconds.
+    pub timestamp: u64,
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The charged amount.
+    pub amount: u64,
+    // The lifecycle event sequence number.
+    pub event_seq: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was suspended by the charge.
+pub struct SuspendedEvent {
+    // The suspension timestamp in milliseconds.
+    pub timestamp: u64,
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The lifecycle event sequence number.
+    pub event_seq: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription is going to be suspended within the configured lead time.
+pub struct PreSuspendWarningEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The projected suspension timestamp in milliseconds.
+    pub suspends_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The suspended subscription was reactivated.
+pub struct ResumedEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The burned activation fee.
+    pub activation_fee: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was hibernated by the owner.
+pub struct HibernatedEvent {
+    // The hibernation timestamp in milliseconds.
+    pub timestamp: u64,
+    // The subscription ID.
+    pub subscription_id: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The hibernated subscription was woken by the owner.
+pub struct WokenEvent {
+    // The wake timestamp in milliseconds.
+    pub timestamp: u64,
+    // The subscription ID.
+    pub subscription_id: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription webhook was replaced.
+pub struct WebhookEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The subscription data.
+    pub subscription: EventSubscription,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription settings were updated by the owner.
+pub struct UpdatedEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was transferred to another owner.
+pub struct OwnerChangedEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The previous owner address.
+    pub previous_owner: Address,
+    // The new owner address.
+    pub owner: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was cancelled by the owner.
+pub struct CancelledEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The refunded part of the activation fee.
+    pub activation_refund: u64,
+    // The lifecycle event sequence number.
+    pub event_seq: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was terminated by the admin.
+pub struct ForceCancelledEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The address receiving the refund.
+    pub refund_to: Address,
+    // The refunded amount.
+    pub amount: u64,
+    // The termination reason code.
+    pub reason: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was merged into another subscription of the owner.
+pub struct MergedEvent {
+    // The ID of the subscription receiving the balance.
+    pub subscription_id: u64,
+    // The ID of the removed subscription.
+    pub merged_id: u64,
+    // The moved balance.
+    pub amount: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription balance was corrected by the admin.
+pub struct AdjustedEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The balance before the correction.
+    pub previous_balance: u64,
+    // The corrected balance.
+    pub balance: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The balance of the suspended subscription was withdrawn by the owner.
+pub struct WithdrawnEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The withdrawn amount.
+    pub amount: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was billed at the owner fee cap.
+pub struct FeeCapHitEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The uncapped daily fee.
+    pub fee: u64,
+    // The owner fee cap.
+    pub max_acceptable_fee: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The subscription was skipped by the charge, published only with the debug events enabled.
+pub struct ChargeSkippedEvent {
+    // The subscription ID.
+    pub subscription_id: u64,
+    // The skip reason.
+    pub reason: Symbol,
+}
@@ -4,4 +4,7 @@ pub mod subscription;
 pub mod error;
 pub mod contract_config;
 pub mod subscription_init_params;
-pub mod subscription_status;
\ No newline at end of file
+pub mod subscription_status;
+pub mod suspend_behavior;
+pub mod events;
+pub mod payment_token;
@@ -0,0 +1,11 @@
+use soroban_sdk::{contracttype, Address};
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// The token a new subscription is paid in.
+pub enum PaymentToken {
+    // The contract token.
+    Default,
+    // The custom token address.
+    Custom(Address),
+}
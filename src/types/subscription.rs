@@ -2,6 +2,9 @@ use soroban_sdk::{contracttype, Address, Bytes};
 
 use super::{subscription_status::SubscriptionStatus, ticker_asset::TickerAsset};
 
+// The current storage format of the subscription, increment it whenever the struct changes.
+pub const SUBSCRIPTION_FORMAT_VERSION: u32 = 11;
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 
@@ -15,6 +18,10 @@ pub struct Subscription {
     pub quote: TickerAsset,
     // The threshold in percentage.
     pub threshold: u32,
+    // The upward move threshold in percentage.
+    pub threshold_up: u32,
+    // The downward move threshold in percentage.
+    pub threshold_down: u32,
     // The heartbeat in minutes.
     pub heartbeat: u32,
     // The webhook.
@@ -24,5 +31,37 @@ pub struct Subscription {
     // The subscription status.
     pub status: SubscriptionStatus,
     // The last change timestamp.
-    pub updated: u64
+    pub updated: u64,
+    // The last deposit timestamp.
+    pub last_deposit: u64,
+    // The processing priority, higher values are processed first.
+    pub priority: u32,
+    // The ledger the subscription storage is kept alive until.
+    pub live_until: u32,
+    // The storage format version.
+    pub format_version: u32,
+    // The billing interval in milliseconds.
+    pub charge_interval_ms: u64,
+    // The webhook expiration timestamp in milliseconds, if the webhook has to be rotated.
+    pub webhook_expires_at: Option<u64>,
+    // The sequence number of the last deposit.
+    pub last_deposit_seq: u64,
+    // The maximum daily fee the owner accepts to be billed, if capped.
+    pub max_acceptable_fee: Option<u64>,
+    // Whether the owner has been warned about the upcoming suspension since the last deposit.
+    pub warned: bool,
+    // The token the subscription is paid in.
+    pub token: Address,
+    // The timestamp in milliseconds the underfunded subscription is suspended at, if within the suspension grace period.
+    pub grace_until: Option<u64>,
+    // The bookkeeping label, opaque to the contract, empty if not set.
+    pub label: Bytes,
+    // The effective daily fee counted in the projected revenue while the capped subscription is active, zero if uncapped.
+    pub tracked_fee: u64,
+    // The activation fee charged on creation.
+    pub activation_fee: u64,
+    // The creation timestamp in milliseconds, the cancellation grace period runs from it.
+    pub activated_at: u64,
+    // The refundable share of the activation fee kept in custody until the cancellation grace period ends.
+    pub refund_hold: u64,
 }
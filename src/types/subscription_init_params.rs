@@ -1,6 +1,6 @@
 use soroban_sdk::{contracttype, Address, Bytes};
 
-use super::ticker_asset::TickerAsset;
+use super::{payment_token::PaymentToken, ticker_asset::TickerAsset};
 
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -15,8 +15,24 @@ pub struct SubscriptionInitParams {
     pub quote: TickerAsset,
     // The threshold in percentage.
     pub threshold: u32,
+    // The upward move threshold in percentage, defaults to the threshold.
+    pub threshold_up: Option<u32>,
+    // The downward move threshold in percentage, defaults to the threshold.
+    pub threshold_down: Option<u32>,
     // The heartbeat in minutes.
     pub heartbeat: u32,
     // The webhook.
     pub webhook: Bytes,
+    // The processing priority, higher values are processed first.
+    pub priority: u32,
+    // The billing interval in milliseconds, defaults to the base asset interval or one day.
+    pub charge_interval_ms: Option<u64>,
+    // The webhook expiration timestamp in milliseconds, if the webhook has to be rotated.
+    pub webhook_expires_at: Option<u64>,
+    // The maximum daily fee the owner accepts to be billed, if capped.
+    pub max_acceptable_fee: Option<u64>,
+    // The token the subscription is paid in.
+    pub token: PaymentToken,
+    // The bookkeeping label, empty if not set.
+    pub label: Bytes,
 }
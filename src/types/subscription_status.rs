@@ -5,5 +5,6 @@ use soroban_sdk::contracttype;
 #[derive(Clone, Debug, Eq, PartialEq, Copy)]
 pub enum SubscriptionStatus {
     Active = 0,
-    Suspended = 1
+    Suspended = 1,
+    Hibernated = 2
 }
\ No newline at end of file
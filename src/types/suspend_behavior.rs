@@ -0,0 +1,13 @@
+use soroban_sdk::{contracttype, Address};
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+// What happens to the remaining balance of a subscription suspended by the charge.
+pub enum SuspendBehavior {
+    // The balance is kept for reactivation or cancellation.
+    Retain,
+    // The balance is forfeited to the treasury address.
+    DrainToTreasury(Address),
+    // The balance is refunded to the owner.
+    Refund,
+}